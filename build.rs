@@ -3,12 +3,62 @@ use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
 
+/// The MPU stack guard's size, unless `STELLARIS_STACK_GUARD_SIZE` says
+/// otherwise.
+const DEFAULT_GUARD_SIZE: u32 = 256;
+
 fn main() {
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
 
     let memory_x = include_bytes!("memory.x.in");
     let mut f = File::create(out_dir.join("memory.x")).unwrap();
+    // A plain definition takes precedence over the PROVIDEs in memory.x.in.
+    for (var, symbol) in &[
+        ("STELLARIS_STACK_SIZE", "_stack_size"),
+        ("STELLARIS_HEAP_SIZE", "_heap_size"),
+    ] {
+        println!("cargo:rerun-if-env-changed={}", var);
+        if let Ok(value) = env::var(var) {
+            let size = parse_size(&value)
+                .unwrap_or_else(|| panic!("{}={:?} is not a valid size", var, value));
+            writeln!(f, "{} = {:#x};", symbol, size).unwrap();
+        }
+    }
+    // The guard size is needed by the code as well as the linker script.
+    println!("cargo:rerun-if-env-changed=STELLARIS_STACK_GUARD_SIZE");
+    let guard_size = match env::var("STELLARIS_STACK_GUARD_SIZE") {
+        Ok(value) => parse_size(&value)
+            .filter(|size| size.is_power_of_two() && *size >= 32)
+            .unwrap_or_else(|| {
+                panic!(
+                    "STELLARIS_STACK_GUARD_SIZE={:?} is not a power of two of at least 32",
+                    value
+                )
+            }),
+        Err(_) => DEFAULT_GUARD_SIZE,
+    };
+    writeln!(f, "_stack_guard_size = {:#x};", guard_size).unwrap();
     f.write_all(memory_x).unwrap();
+    std::fs::write(
+        out_dir.join("stack_guard_size.rs"),
+        format!("{}", guard_size),
+    )
+    .unwrap();
 
     println!("cargo:rustc-link-search={}", out_dir.display());
+    println!("cargo:rerun-if-changed=memory.x.in");
+}
+
+/// Accepts decimal or 0x-prefixed hex, with an optional K suffix.
+fn parse_size(value: &str) -> Option<u32> {
+    let value = value.trim();
+    let (value, scale) = match value.strip_suffix(|c| c == 'K' || c == 'k') {
+        Some(value) => (value, 1024),
+        None => (value, 1),
+    };
+    let size = match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => value.parse().ok()?,
+    };
+    size.checked_mul(scale)
 }
@@ -0,0 +1,533 @@
+//! LED effects - blinking, breathing, Morse and so on - as a pure function
+//! of time, so the timing can be tested on the host. The board crate's
+//! `effects` module plays them on the LED from a timer interrupt.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use crate::{Rgb, BLACK};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Something for the LED to do.
+#[derive(Debug, Clone, Copy)]
+pub enum Effect {
+    /// All off.
+    Off,
+    /// A steady colour.
+    Solid(Rgb),
+    /// Flash on and off, forever.
+    Blink {
+        /// The colour when on.
+        colour: Rgb,
+        /// How long to stay on.
+        on_ms: u32,
+        /// How long to stay off.
+        off_ms: u32,
+    },
+    /// Fade smoothly up to the colour and back down again, forever.
+    Breathe {
+        /// The colour at the brightest point.
+        colour: Rgb,
+        /// The time for one full breath, in and out.
+        period_ms: u32,
+    },
+    /// Go round the colour wheel at full brightness, forever.
+    ColourCycle {
+        /// The time for one lap of the wheel.
+        period_ms: u32,
+    },
+    /// Flash a message in Morse code. Letters, digits and spaces are
+    /// understood; anything else is skipped.
+    Morse {
+        /// The colour to flash.
+        colour: Rgb,
+        /// The message.
+        message: &'static str,
+        /// The length of a dot, in milliseconds. A dash is three dots.
+        unit_ms: u32,
+        /// Start again (after a word gap) once finished.
+        repeat: bool,
+    },
+    /// Play a list of steps.
+    Sequence {
+        /// The steps, in order.
+        steps: &'static [Step],
+        /// Start again once the last step is done.
+        repeat: bool,
+    },
+}
+
+/// One step in an `Effect::Sequence`.
+#[derive(Debug, Clone, Copy)]
+pub struct Step {
+    /// The colour to show.
+    pub colour: Rgb,
+    /// How long this step lasts.
+    pub duration_ms: u32,
+    /// Fade from the previous step's colour to this one over the step,
+    /// rather than changing straight away.
+    pub fade: bool,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// Morse code for A-Z then 0-9.
+static MORSE: [&str; 36] = [
+    ".-", "-...", "-.-.", "-..", ".", "..-.", "--.", "....", "..", ".---", "-.-", ".-..", "--",
+    "-.", "---", ".--.", "--.-", ".-.", "...", "-", "..-", "...-", ".--", "-..-", "-.--", "--..",
+    "-----", ".----", "..---", "...--", "....-", ".....", "-....", "--...", "---..", "----.",
+];
+
+// In units of a dot.
+const DASH_UNITS: u32 = 3;
+const SYMBOL_GAP_UNITS: u32 = 1;
+const LETTER_GAP_UNITS: u32 = 3;
+const WORD_GAP_UNITS: u32 = 7;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Effect {
+    /// The colour to show `t` milliseconds after the effect started, or
+    /// `None` if it has finished.
+    pub fn colour_at(&self, t: u32) -> Option<Rgb> {
+        match *self {
+            Effect::Off => Some(BLACK),
+            Effect::Solid(colour) => Some(colour),
+            Effect::Blink {
+                colour,
+                on_ms,
+                off_ms,
+            } => {
+                let period = (u64::from(on_ms) + u64::from(off_ms)).max(1);
+                Some(if u64::from(t) % period < u64::from(on_ms) {
+                    colour
+                } else {
+                    BLACK
+                })
+            }
+            Effect::Breathe { colour, period_ms } => {
+                let period = u64::from(period_ms.max(2));
+                let half = period / 2;
+                let phase = u64::from(t) % period;
+                let level = if phase < half {
+                    phase * 255 / half
+                } else {
+                    (period - phase) * 255 / (period - half)
+                };
+                Some(dim(colour, level.min(255) as u8))
+            }
+            Effect::ColourCycle { period_ms } => {
+                let period = period_ms.max(1);
+                let hue = ((t % period) as u64 * 360 / u64::from(period)) as u16;
+                Some(Rgb::from_hsv(hue, 255, 255))
+            }
+            Effect::Morse {
+                colour,
+                message,
+                unit_ms,
+                repeat,
+            } => {
+                let unit = t / unit_ms.max(1);
+                let length = morse_length(message);
+                if length == 0 {
+                    return None;
+                }
+                let unit = if repeat {
+                    unit % (length + WORD_GAP_UNITS)
+                } else if unit < length {
+                    unit
+                } else {
+                    return None;
+                };
+                Some(if morse_is_on(message, unit) {
+                    colour
+                } else {
+                    BLACK
+                })
+            }
+            Effect::Sequence { steps, repeat } => {
+                let total: u64 = steps.iter().map(|s| u64::from(s.duration_ms)).sum();
+                if total == 0 {
+                    return None;
+                }
+                let t = u64::from(t);
+                let t = if repeat {
+                    t % total
+                } else if t < total {
+                    t
+                } else {
+                    return None;
+                };
+                let mut start = 0;
+                let mut previous = steps.last().filter(|_| repeat).map_or(BLACK, |s| s.colour);
+                for step in steps {
+                    let duration = u64::from(step.duration_ms);
+                    if t < start + duration {
+                        return Some(if step.fade {
+                            let progress = (t - start) * 255 / duration;
+                            blend(previous, step.colour, progress as u8)
+                        } else {
+                            step.colour
+                        });
+                    }
+                    start += duration;
+                    previous = step.colour;
+                }
+                None
+            }
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Scale a colour's brightness, 255 being unchanged.
+fn dim(colour: Rgb, level: u8) -> Rgb {
+    colour.calibrate(Rgb::new(level, level, level))
+}
+
+/// Mix from `from` (at 0) to `to` (at 255).
+fn blend(from: Rgb, to: Rgb, amount: u8) -> Rgb {
+    let mix = |a: u8, b: u8| -> u8 {
+        let a = u32::from(a);
+        let b = u32::from(b);
+        let amount = u32::from(amount);
+        ((a * (255 - amount) + b * amount) / 255) as u8
+    };
+    Rgb::new(
+        mix(from.red, to.red),
+        mix(from.green, to.green),
+        mix(from.blue, to.blue),
+    )
+}
+
+fn morse_code(c: char) -> Option<&'static str> {
+    match c.to_ascii_uppercase() {
+        c @ 'A'..='Z' => Some(MORSE[c as usize - 'A' as usize]),
+        c @ '0'..='9' => Some(MORSE[26 + c as usize - '0' as usize]),
+        _ => None,
+    }
+}
+
+/// Walk the message, calling `f(on, units)` for each on or off period.
+/// Stops early if `f` returns `false`.
+fn morse_walk<F: FnMut(bool, u32) -> bool>(message: &str, mut f: F) {
+    let mut first_letter = true;
+    let mut word_gap = false;
+    for c in message.chars() {
+        if c == ' ' {
+            word_gap = !first_letter;
+            continue;
+        }
+        let code = match morse_code(c) {
+            Some(code) => code,
+            None => continue,
+        };
+        if !first_letter {
+            let gap = if word_gap {
+                WORD_GAP_UNITS
+            } else {
+                LETTER_GAP_UNITS
+            };
+            if !f(false, gap) {
+                return;
+            }
+        }
+        first_letter = false;
+        word_gap = false;
+        for (i, symbol) in code.bytes().enumerate() {
+            if i > 0 && !f(false, SYMBOL_GAP_UNITS) {
+                return;
+            }
+            let units = if symbol == b'-' { DASH_UNITS } else { 1 };
+            if !f(true, units) {
+                return;
+            }
+        }
+    }
+}
+
+/// The length of the whole message, in units.
+fn morse_length(message: &str) -> u32 {
+    let mut total = 0;
+    morse_walk(message, |_, units| {
+        total += units;
+        true
+    });
+    total
+}
+
+/// Is the LED on at `unit` units into the message?
+fn morse_is_on(message: &str, unit: u32) -> bool {
+    let mut start = 0;
+    let mut result = false;
+    morse_walk(message, |on, units| {
+        if unit < start + units {
+            result = on;
+            false
+        } else {
+            start += units;
+            true
+        }
+    });
+    result
+}
+
+// ****************************************************************************
+//
+// Tests
+//
+// ****************************************************************************
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::WHITE;
+    use std::string::String;
+
+    const ON: Rgb = Rgb::new(0, 0, 255);
+
+    fn morse(message: &'static str, repeat: bool) -> Effect {
+        Effect::Morse {
+            colour: ON,
+            message,
+            unit_ms: 100,
+            repeat,
+        }
+    }
+
+    /// The on/off pattern, one character per unit, until the effect
+    /// finishes or `units` have gone by.
+    fn pattern(effect: &Effect, units: u32) -> String {
+        (0..units)
+            .map_while(|unit| effect.colour_at(unit * 100 + 50))
+            .map(|colour| if colour == ON { '#' } else { '_' })
+            .collect()
+    }
+
+    #[test]
+    fn morse_sos() {
+        assert_eq!(
+            pattern(&morse("SOS", false), 100),
+            "#_#_#___###_###_###___#_#_#"
+        );
+    }
+
+    #[test]
+    fn morse_word_gap_is_seven_units() {
+        assert_eq!(pattern(&morse("E E", false), 100), "#_______#");
+        // Extra and leading spaces don't lengthen it.
+        assert_eq!(pattern(&morse(" E   e", false), 100), "#_______#");
+    }
+
+    #[test]
+    fn morse_skips_unknown_characters() {
+        assert_eq!(pattern(&morse("E?T", false), 100), "#___###");
+        assert_eq!(pattern(&morse("5", false), 100), "#_#_#_#_#");
+        assert_eq!(morse("?!", false).colour_at(0), None);
+    }
+
+    #[test]
+    fn morse_unit_boundaries() {
+        let effect = morse("E", false);
+        assert_eq!(effect.colour_at(0), Some(ON));
+        assert_eq!(effect.colour_at(99), Some(ON));
+        assert_eq!(effect.colour_at(100), None);
+    }
+
+    #[test]
+    fn morse_repeats_after_a_word_gap() {
+        assert_eq!(pattern(&morse("ET", true), 22), "#___###_______#___###_");
+        // And keeps going well past the first lap.
+        let effect = morse("ET", true);
+        assert_eq!(effect.colour_at(14 * 100 * 1000), Some(ON));
+    }
+
+    const RED: Rgb = Rgb::new(255, 0, 0);
+    const GREEN: Rgb = Rgb::new(0, 255, 0);
+    const BLUE: Rgb = Rgb::new(0, 0, 255);
+
+    static STEPS: [Step; 3] = [
+        Step {
+            colour: RED,
+            duration_ms: 100,
+            fade: true,
+        },
+        Step {
+            colour: GREEN,
+            duration_ms: 200,
+            fade: false,
+        },
+        Step {
+            colour: BLUE,
+            duration_ms: 100,
+            fade: true,
+        },
+    ];
+
+    #[test]
+    fn sequence_steps() {
+        let effect = Effect::Sequence {
+            steps: &STEPS,
+            repeat: false,
+        };
+        assert_eq!(effect.colour_at(0), Some(BLACK));
+        assert_eq!(effect.colour_at(100), Some(GREEN));
+        assert_eq!(effect.colour_at(299), Some(GREEN));
+        assert_eq!(effect.colour_at(300), Some(GREEN));
+        assert_eq!(effect.colour_at(350), Some(Rgb::new(0, 128, 127)));
+        assert_eq!(effect.colour_at(399), Some(Rgb::new(0, 3, 252)));
+    }
+
+    #[test]
+    fn sequence_without_repeat_finishes() {
+        let effect = Effect::Sequence {
+            steps: &STEPS,
+            repeat: false,
+        };
+        // Fading in from off the first time.
+        assert_eq!(effect.colour_at(50), Some(Rgb::new(127, 0, 0)));
+        assert_eq!(effect.colour_at(400), None);
+        assert_eq!(effect.colour_at(u32::MAX), None);
+    }
+
+    #[test]
+    fn sequence_wraps_round() {
+        let effect = Effect::Sequence {
+            steps: &STEPS,
+            repeat: true,
+        };
+        // The first step fades from the last step's colour, every time.
+        assert_eq!(effect.colour_at(0), Some(BLUE));
+        assert_eq!(effect.colour_at(50), Some(Rgb::new(127, 0, 128)));
+        assert_eq!(effect.colour_at(400), Some(BLUE));
+        assert_eq!(effect.colour_at(450), Some(Rgb::new(127, 0, 128)));
+        for t in 0..400 {
+            assert_eq!(effect.colour_at(t), effect.colour_at(t + 400 * 7));
+        }
+    }
+
+    #[test]
+    fn empty_sequence_finishes_at_once() {
+        let effect = Effect::Sequence {
+            steps: &[],
+            repeat: true,
+        };
+        assert_eq!(effect.colour_at(0), None);
+    }
+
+    #[test]
+    fn blink() {
+        let effect = Effect::Blink {
+            colour: RED,
+            on_ms: 100,
+            off_ms: 50,
+        };
+        assert_eq!(effect.colour_at(0), Some(RED));
+        assert_eq!(effect.colour_at(99), Some(RED));
+        assert_eq!(effect.colour_at(100), Some(BLACK));
+        assert_eq!(effect.colour_at(149), Some(BLACK));
+        assert_eq!(effect.colour_at(150), Some(RED));
+    }
+
+    #[test]
+    fn breathe() {
+        let effect = Effect::Breathe {
+            colour: WHITE,
+            period_ms: 1000,
+        };
+        assert_eq!(effect.colour_at(0), Some(BLACK));
+        assert_eq!(effect.colour_at(250), Some(Rgb::new(127, 127, 127)));
+        assert_eq!(effect.colour_at(500), Some(WHITE));
+        assert_eq!(effect.colour_at(1000), Some(BLACK));
+    }
+
+    #[test]
+    fn huge_durations_dont_overflow() {
+        let effect = Effect::Blink {
+            colour: RED,
+            on_ms: u32::MAX,
+            off_ms: u32::MAX,
+        };
+        assert_eq!(effect.colour_at(u32::MAX - 1), Some(RED));
+        assert_eq!(effect.colour_at(u32::MAX), Some(BLACK));
+
+        let effect = Effect::Breathe {
+            colour: WHITE,
+            period_ms: u32::MAX,
+        };
+        assert_eq!(
+            effect.colour_at(u32::MAX / 4),
+            Some(Rgb::new(127, 127, 127))
+        );
+        assert_eq!(effect.colour_at(u32::MAX / 2), Some(WHITE));
+        assert_eq!(effect.colour_at(u32::MAX - 1), Some(BLACK));
+
+        static LONG: [Step; 2] = [
+            Step {
+                colour: RED,
+                duration_ms: u32::MAX,
+                fade: true,
+            },
+            Step {
+                colour: BLUE,
+                duration_ms: u32::MAX,
+                fade: true,
+            },
+        ];
+        let effect = Effect::Sequence {
+            steps: &LONG,
+            repeat: true,
+        };
+        assert_eq!(effect.colour_at(u32::MAX / 2), Some(Rgb::new(127, 0, 128)));
+        assert_eq!(effect.colour_at(u32::MAX - 1), Some(Rgb::new(254, 0, 1)));
+        let effect = Effect::Sequence {
+            steps: &LONG,
+            repeat: false,
+        };
+        assert_eq!(effect.colour_at(u32::MAX), Some(RED));
+    }
+}
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,245 @@
+//! Colour maths and timed effects for the Launchpad's RGB LED, kept
+//! separate from the board support crate so they can be unit tested on the
+//! host:
+//!
+//! ```text
+//! cargo test -p stellaris-launchpad-colour --target x86_64-unknown-linux-gnu
+//! ```
+
+#![no_std]
+#![deny(missing_docs)]
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+pub mod effect;
+
+pub use effect::{Effect, Step};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A colour, with each channel from 0 (off) to 255 (full).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb {
+    /// The red channel.
+    pub red: u8,
+    /// The green channel.
+    pub green: u8,
+    /// The blue channel.
+    pub blue: u8,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// Full brightness on every channel.
+pub const WHITE: Rgb = Rgb::new(255, 255, 255);
+
+/// Everything off.
+pub const BLACK: Rgb = Rgb::new(0, 0, 0);
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// `(i / 255) ^ 2.2`, scaled to 0..=65535.
+#[rustfmt::skip]
+static GAMMA: [u16; 256] = [
+    0, 0, 2, 4, 7, 11, 17, 24,
+    32, 42, 53, 65, 79, 94, 111, 129,
+    148, 169, 192, 216, 242, 270, 299, 330,
+    362, 396, 432, 469, 508, 549, 591, 635,
+    681, 729, 779, 830, 883, 938, 995, 1053,
+    1113, 1175, 1239, 1305, 1373, 1443, 1514, 1587,
+    1663, 1740, 1819, 1900, 1983, 2068, 2155, 2243,
+    2334, 2427, 2521, 2618, 2717, 2817, 2920, 3024,
+    3131, 3240, 3350, 3463, 3578, 3694, 3813, 3934,
+    4057, 4182, 4309, 4438, 4570, 4703, 4838, 4976,
+    5115, 5257, 5401, 5547, 5695, 5845, 5998, 6152,
+    6309, 6468, 6629, 6792, 6957, 7124, 7294, 7466,
+    7640, 7816, 7994, 8175, 8358, 8543, 8730, 8919,
+    9111, 9305, 9501, 9699, 9900, 10102, 10307, 10515,
+    10724, 10936, 11150, 11366, 11585, 11806, 12029, 12254,
+    12482, 12712, 12944, 13179, 13416, 13655, 13896, 14140,
+    14386, 14635, 14885, 15138, 15394, 15652, 15912, 16174,
+    16439, 16706, 16975, 17247, 17521, 17798, 18077, 18358,
+    18642, 18928, 19216, 19507, 19800, 20095, 20393, 20694,
+    20996, 21301, 21609, 21919, 22231, 22546, 22863, 23182,
+    23504, 23829, 24156, 24485, 24817, 25151, 25487, 25826,
+    26168, 26512, 26858, 27207, 27558, 27912, 28268, 28627,
+    28988, 29351, 29717, 30086, 30457, 30830, 31206, 31585,
+    31966, 32349, 32735, 33124, 33514, 33908, 34304, 34702,
+    35103, 35507, 35913, 36321, 36732, 37146, 37562, 37981,
+    38402, 38825, 39252, 39680, 40112, 40546, 40982, 41421,
+    41862, 42306, 42753, 43202, 43654, 44108, 44565, 45025,
+    45487, 45951, 46418, 46888, 47360, 47835, 48313, 48793,
+    49275, 49761, 50249, 50739, 51232, 51728, 52226, 52727,
+    53230, 53736, 54245, 54756, 55270, 55787, 56306, 56828,
+    57352, 57879, 58409, 58941, 59476, 60014, 60554, 61097,
+    61642, 62190, 62741, 63295, 63851, 64410, 64971, 65535,
+];
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Rgb {
+    /// Make a colour from its three channels.
+    pub const fn new(red: u8, green: u8, blue: u8) -> Rgb {
+        Rgb { red, green, blue }
+    }
+
+    /// Convert from hue (in degrees - values of 360 and above wrap round),
+    /// saturation and value (both 0 to 255).
+    pub fn from_hsv(hue: u16, saturation: u8, value: u8) -> Rgb {
+        if saturation == 0 {
+            return Rgb::new(value, value, value);
+        }
+        let hue = u32::from(hue % 360);
+        let s = u32::from(saturation);
+        let v = u32::from(value);
+        // How far through this 60 degree sector we are, 0 to 255.
+        let f = (hue % 60) * 255 / 60;
+        let p = (v * (255 - s) / 255) as u8;
+        let q = (v * (255 - s * f / 255) / 255) as u8;
+        let t = (v * (255 - s * (255 - f) / 255) / 255) as u8;
+        match hue / 60 {
+            0 => Rgb::new(value, t, p),
+            1 => Rgb::new(q, value, p),
+            2 => Rgb::new(p, value, t),
+            3 => Rgb::new(p, q, value),
+            4 => Rgb::new(t, p, value),
+            _ => Rgb::new(value, p, q),
+        }
+    }
+
+    /// Scale each channel by the matching channel of `full`, where 255 means
+    /// unchanged. Use this to balance LEDs of different brightness.
+    pub fn calibrate(self, full: Rgb) -> Rgb {
+        Rgb::new(
+            scale(self.red, full.red),
+            scale(self.green, full.green),
+            scale(self.blue, full.blue),
+        )
+    }
+}
+
+/// Map a perceived brightness (0 to 255) to a PWM duty (0 to 65535), so
+/// that equal steps in `level` look like equal steps in brightness.
+pub fn gamma(level: u8) -> u16 {
+    GAMMA[usize::from(level)]
+}
+
+/// A duty (0 to 65535) directly proportional to `level` (0 to 255), for when
+/// gamma correction isn't wanted.
+pub fn linear(level: u8) -> u16 {
+    u16::from(level) * 257
+}
+
+/// `level * full / 255`, rounded to nearest.
+pub fn scale(level: u8, full: u8) -> u8 {
+    ((u32::from(level) * u32::from(full) + 127) / 255) as u8
+}
+
+// ****************************************************************************
+//
+// Tests
+//
+// ****************************************************************************
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primaries() {
+        assert_eq!(Rgb::from_hsv(0, 255, 255), Rgb::new(255, 0, 0));
+        assert_eq!(Rgb::from_hsv(120, 255, 255), Rgb::new(0, 255, 0));
+        assert_eq!(Rgb::from_hsv(240, 255, 255), Rgb::new(0, 0, 255));
+    }
+
+    #[test]
+    fn secondaries() {
+        assert_eq!(Rgb::from_hsv(60, 255, 255), Rgb::new(255, 255, 0));
+        assert_eq!(Rgb::from_hsv(180, 255, 255), Rgb::new(0, 255, 255));
+        assert_eq!(Rgb::from_hsv(300, 255, 255), Rgb::new(255, 0, 255));
+    }
+
+    #[test]
+    fn hue_wraps() {
+        assert_eq!(Rgb::from_hsv(360, 255, 255), Rgb::from_hsv(0, 255, 255));
+        assert_eq!(Rgb::from_hsv(725, 255, 255), Rgb::from_hsv(5, 255, 255));
+    }
+
+    #[test]
+    fn in_between() {
+        assert_eq!(Rgb::from_hsv(30, 255, 255), Rgb::new(255, 127, 0));
+        assert_eq!(Rgb::from_hsv(210, 255, 255), Rgb::new(0, 128, 255));
+    }
+
+    #[test]
+    fn unsaturated_is_grey() {
+        for hue in (0..360).step_by(15) {
+            assert_eq!(Rgb::from_hsv(hue, 0, 100), Rgb::new(100, 100, 100));
+        }
+    }
+
+    #[test]
+    fn zero_value_is_black() {
+        for hue in (0..360).step_by(15) {
+            assert_eq!(Rgb::from_hsv(hue, 255, 0), BLACK);
+        }
+    }
+
+    #[test]
+    fn half_saturation() {
+        assert_eq!(Rgb::from_hsv(0, 128, 255), Rgb::new(255, 127, 127));
+    }
+
+    #[test]
+    fn gamma_ends_and_order() {
+        assert_eq!(gamma(0), 0);
+        assert_eq!(gamma(255), 65535);
+        for level in 1..=255u8 {
+            assert!(gamma(level) >= gamma(level - 1));
+        }
+        // Half brightness needs much less than half the duty.
+        assert!(gamma(128) < 65535 / 4);
+    }
+
+    #[test]
+    fn linear_ends() {
+        assert_eq!(linear(0), 0);
+        assert_eq!(linear(255), 65535);
+    }
+
+    #[test]
+    fn calibration() {
+        assert_eq!(
+            WHITE.calibrate(Rgb::new(255, 128, 64)),
+            Rgb::new(255, 128, 64)
+        );
+        assert_eq!(Rgb::new(10, 20, 30).calibrate(WHITE), Rgb::new(10, 20, 30));
+        assert_eq!(Rgb::new(200, 200, 200).calibrate(BLACK), BLACK);
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
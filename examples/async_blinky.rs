@@ -0,0 +1,101 @@
+//! Three things at once, without a busy loop: blink the LED, toggle red
+//! whenever SW1 is pressed, and echo whatever arrives on UART0. Each is an
+//! `async` task on the crate's executor, and the core sleeps in between.
+
+#![no_std]
+#![no_main]
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::cell::RefCell;
+use core::fmt::Write;
+use core::pin::pin;
+
+use stellaris_launchpad::board::edges::{self, Trigger};
+use stellaris_launchpad::board::time::{self, Duration};
+use stellaris_launchpad::board::uart::{self, Port};
+use stellaris_launchpad::board::{timers, Board, Button, Led, RgbLed};
+use stellaris_launchpad::common::executor::Executor;
+use tm4c123x_hal::gpio::GpioExt;
+use tm4c123x_hal::serial;
+use tm4c123x_hal::time::Bps;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+#[stellaris_launchpad::main]
+fn main(board: Board) {
+    time::init(board.WTIMER5, &board.power_control);
+    timers::start(board.TIMER3, &board.power_control);
+    let mut pins_a = board.GPIO_PORTA.split(&board.power_control);
+    let serial = serial::Serial::uart0(
+        board.UART0,
+        pins_a.pa1.into_af_push_pull(&mut pins_a.control),
+        pins_a.pa0.into_af_push_pull(&mut pins_a.control),
+        (),
+        (),
+        Bps(115200),
+        serial::NewlineMode::SwapLFtoCRLF,
+        stellaris_launchpad::board::clocks(),
+        &board.power_control,
+    );
+    let led = RefCell::new(RgbLed::new(board.led_red, board.led_green, board.led_blue));
+
+    let mut blink = pin!(blink(&led));
+    let mut button = pin!(button(&led));
+    let mut echo = pin!(echo(serial));
+    let mut executor = Executor::new();
+    executor.spawn(blink.as_mut()).unwrap();
+    executor.spawn(button.as_mut()).unwrap();
+    executor.spawn(echo.as_mut()).unwrap();
+    executor.run();
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+async fn blink(led: &RefCell<RgbLed>) {
+    loop {
+        led.borrow_mut().toggle(Led::Blue);
+        timers::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+async fn button(led: &RefCell<RgbLed>) {
+    loop {
+        edges::edge(Button::One, Trigger::Press).await;
+        led.borrow_mut().toggle(Led::Red);
+        // Crude debounce: ignore the switch for a moment.
+        timers::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+async fn echo<W: Write>(mut serial: W) {
+    let _ = writeln!(serial, "Type something...");
+    loop {
+        match uart::read_byte(Port::Uart0).await {
+            Ok(byte) => {
+                let _ = write!(serial, "{}", byte as char);
+            }
+            Err(e) => {
+                let _ = writeln!(serial, "\nError: {:?}", e);
+            }
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -1,34 +1,41 @@
 #![no_std]
 #![no_main]
-#![feature(alloc_error_handler)]
 
 extern crate embedded_hal;
 extern crate stellaris_launchpad;
 extern crate tm4c123x_hal;
 
-use core::alloc::Layout;
 use embedded_hal::blocking::delay::DelayMs;
-use embedded_hal::digital::v2::{InputPin, OutputPin};
+use stellaris_launchpad::board::{Button, ButtonEvent, ButtonTiming, Buttons, Led, RgbLed};
 
-#[no_mangle]
-pub fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
+const POLL_MS: u32 = 10;
+
+#[stellaris_launchpad::main]
+fn main(mut board: stellaris_launchpad::board::Board) {
     let mut delay = tm4c123x_hal::delay::Delay::new(
         board.core_peripherals.SYST,
         stellaris_launchpad::board::clocks(),
     );
+    let mut buttons = Buttons::new(board.button_one, board.button_two, ButtonTiming::default());
+    let mut led = RgbLed::new(board.led_red, board.led_green, board.led_blue);
+    let mut now = 0u32;
 
     loop {
-        if board.button_one.is_high().unwrap() {
-            board.led_red.set_low().unwrap();
-            delay.delay_ms(50u32);
-        } else {
-            board.led_red.set_high().unwrap();
-            delay.delay_ms(50u32);
+        buttons.poll(now);
+        while let Some(event) = buttons.next_event() {
+            match event {
+                ButtonEvent::Pressed(Button::One) => led.set(Led::Red, true),
+                ButtonEvent::Released(Button::One) => led.set(Led::Red, false),
+                ButtonEvent::Pressed(Button::Two) => led.toggle(Led::Blue),
+                ButtonEvent::DoubleClick(_) => led.toggle(Led::Green),
+                ButtonEvent::LongPress(_) | ButtonEvent::Chord => {
+                    led.set(Led::Blue, false);
+                    led.set(Led::Green, false);
+                }
+                _ => {}
+            }
         }
+        delay.delay_ms(POLL_MS);
+        now = now.wrapping_add(POLL_MS);
     }
 }
-
-#[alloc_error_handler]
-fn oom(_: Layout) -> ! {
-    stellaris_launchpad::board::panic();
-}
@@ -0,0 +1,76 @@
+//! The console, sent by the µDMA controller. Connect at 115200 bps: each
+//! second a block of text appears, then a line saying how long `println!`
+//! kept the CPU, and how long the UART then took to send it all.
+
+#![no_std]
+#![no_main]
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+#[macro_use]
+extern crate stellaris_launchpad;
+extern crate tm4c123x_hal;
+
+use stellaris_launchpad::board::console;
+use stellaris_launchpad::board::dma::Dma;
+use stellaris_launchpad::board::time::{self, Duration, Timeout};
+use stellaris_launchpad::board::{Board, Led};
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+static TEXT: [&str; 3] = [
+    "The quick brown fox jumps over the lazy dog.",
+    "Pack my box with five dozen liquor jugs.",
+    "How vexingly quick daft zebras jump!",
+];
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+#[stellaris_launchpad::main]
+fn main(board: Board) {
+    let parts = board.split();
+    parts.console.init(&parts.power_control);
+    time::init(parts.timers.wtimer5, &parts.power_control);
+    let dma = Dma::new(parts.other.udma, &parts.power_control);
+    console::use_dma(&dma).unwrap();
+    let mut led = parts.leds.into_rgb_led();
+
+    println!("µDMA console example");
+    loop {
+        led.set(Led::Green, true);
+        let start = time::now();
+        for line in TEXT.iter() {
+            println!("{}", line);
+        }
+        let queued = start.elapsed();
+        console::flush();
+        let sent = start.elapsed();
+        led.set(Led::Green, false);
+        println!(
+            "queued in {} us, sent in {} us",
+            queued.as_micros(),
+            sent.as_micros()
+        );
+
+        let pause = Timeout::after(Duration::from_millis(1000));
+        while !pause.expired() {}
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -1,6 +1,5 @@
 #![no_std]
 #![no_main]
-#![feature(alloc_error_handler)]
 
 #[macro_use]
 extern crate keypad;
@@ -11,12 +10,10 @@ extern crate hd44780_driver;
 extern crate stellaris_launchpad;
 extern crate tm4c123x_hal;
 
-use core::alloc::Layout;
 use embedded_hal::blocking::delay::DelayMs;
 use embedded_hal::digital::v2::{InputPin, OutputPin};
 use hd44780_driver::{Cursor, CursorBlink, Display, DisplayMode, HD44780};
 //use numtoa::NumToA;
-use stellaris_launchpad::board;
 use tm4c123x_hal::gpio::GpioExt;
 
 use tm4c123x_hal::gpio::{
@@ -45,8 +42,8 @@ keypad_struct! {
     }
 }
 
-#[no_mangle]
-pub fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
+#[stellaris_launchpad::main]
+fn main(mut board: stellaris_launchpad::board::Board) {
     let mut delay = tm4c123x_hal::delay::Delay::new(
         board.core_peripherals.SYST,
         stellaris_launchpad::board::clocks(),
@@ -247,8 +244,3 @@ fn get_chess_rank(keypad: &MyKeypad) -> i32 {
         }
     }
 }
-
-#[alloc_error_handler]
-fn oom(_: Layout) -> ! {
-    board::panic();
-}
@@ -1,6 +1,5 @@
 #![no_std]
 #![no_main]
-#![feature(alloc_error_handler)]
 
 #[macro_use]
 extern crate keypad;
@@ -11,12 +10,10 @@ extern crate numtoa;
 extern crate stellaris_launchpad;
 extern crate tm4c123x_hal;
 
-use core::alloc::Layout;
 use embedded_hal::blocking::delay::DelayMs;
 use embedded_hal::digital::v2::InputPin;
 use hd44780_driver::{Cursor, CursorBlink, Display, DisplayMode, HD44780};
 use numtoa::NumToA;
-use stellaris_launchpad::board;
 use tm4c123x_hal::gpio::GpioExt;
 
 use tm4c123x_hal::gpio::{gpioa::PA5, gpioa::PA6, gpioa::PA7, gpiob::PB1, gpioe::PE4, gpioe::PE5};
@@ -37,8 +34,8 @@ keypad_struct! {
     }
 }
 
-#[no_mangle]
-pub fn stellaris_main(board: stellaris_launchpad::board::Board) {
+#[stellaris_launchpad::main]
+fn main(board: stellaris_launchpad::board::Board) {
     let mut delay = tm4c123x_hal::delay::Delay::new(
         board.core_peripherals.SYST,
         stellaris_launchpad::board::clocks(),
@@ -125,8 +122,3 @@ pub fn stellaris_main(board: stellaris_launchpad::board::Board) {
     //    delay.delay_ms(500u32);
     //}
 }
-
-#[alloc_error_handler]
-fn oom(_: Layout) -> ! {
-    board::panic();
-}
@@ -3,7 +3,6 @@
 
 #![no_std]
 #![no_main]
-#![feature(alloc_error_handler)]
 
 // ****************************************************************************
 //
@@ -12,62 +11,43 @@
 // ****************************************************************************
 
 extern crate embedded_hal;
+#[macro_use]
 extern crate stellaris_launchpad;
 extern crate tm4c123x_hal;
 
-use core::alloc::Layout;
-use core::fmt::Write;
 use embedded_hal::blocking::delay::DelayMs;
-use embedded_hal::serial::Read as ReadHal;
 use embedded_hal::Pwm;
-use stellaris_launchpad::board;
-use tm4c123x_hal::gpio::GpioExt;
-use tm4c123x_hal::serial;
-use tm4c123x_hal::time::Bps;
+use stellaris_launchpad::board::console;
 
-#[no_mangle]
-pub fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
-    let mut pins_a = board.GPIO_PORTA.split(&board.power_control);
-    let mut uart = serial::Serial::uart0(
-        board.UART0,
-        pins_a.pa1.into_af_push_pull(&mut pins_a.control),
-        pins_a.pa0.into_af_push_pull(&mut pins_a.control),
-        (),
-        (),
-        Bps(115200),
-        serial::NewlineMode::SwapLFtoCRLF,
-        stellaris_launchpad::board::clocks(),
-        &board.power_control,
-    );
+#[stellaris_launchpad::main]
+fn main(board: stellaris_launchpad::board::Board) {
+    let mut parts = board.split();
+    parts.console.init(&parts.power_control);
     let mut delay = tm4c123x_hal::delay::Delay::new(
-        board.core_peripherals.SYST,
+        parts.core_peripherals.SYST,
         stellaris_launchpad::board::clocks(),
     );
     let mut loops = 0;
 
-    let mut blue_led_pwm = tm4c123x_hal::pwm::Timer::timer1(&board.power_control, board.TIMER1)
-        .into_even(board.led_blue.into_af_push_pull(&mut board.portf_control));
+    let mut blue_led_pwm =
+        tm4c123x_hal::pwm::Timer::timer1(&parts.power_control, parts.timers.timer1)
+            .into_even(parts.leds.blue.into_af_push_pull(&mut parts.leds.control));
 
     blue_led_pwm.set_period(4096u32);
     blue_led_pwm.set_duty((), 0);
     blue_led_pwm.enable(());
 
     let levels = [1u32, 256, 512, 1024, 2048, 4096];
-    uart.write_all("Welcome to Launchpad Blink\n");
+    println!("Welcome to Launchpad Blink");
     loop {
         for level in &levels {
             blue_led_pwm.set_duty((), *level);
-            writeln!(uart, "Hello, world! Loops = {}, level = {}", loops, level).unwrap();
-            while let Ok(ch) = uart.read() {
-                writeln!(uart, "byte read {}", ch).unwrap();
+            info!("Hello, world! Loops = {}, level = {}", loops, level);
+            while let Some(ch) = console::read() {
+                println!("byte read {}", ch);
             }
             loops = loops + 1;
             delay.delay_ms(250u32);
         }
     }
 }
-
-#[alloc_error_handler]
-fn oom(_: Layout) -> ! {
-    board::panic();
-}
@@ -1,21 +1,18 @@
 #![no_std]
 #![no_main]
-#![feature(alloc_error_handler)]
 
 extern crate embedded_hal;
 extern crate hd44780_driver;
 extern crate stellaris_launchpad;
 extern crate tm4c123x_hal;
 
-use core::alloc::Layout;
 use embedded_hal::blocking::delay::DelayMs;
 use embedded_hal::digital::v2::OutputPin;
 use hd44780_driver::{Cursor, CursorBlink, Display, DisplayMode, HD44780};
-use stellaris_launchpad::board;
 use tm4c123x_hal::gpio::GpioExt;
 
-#[no_mangle]
-pub fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
+#[stellaris_launchpad::main]
+fn main(mut board: stellaris_launchpad::board::Board) {
     let mut delay = tm4c123x_hal::delay::Delay::new(
         board.core_peripherals.SYST,
         stellaris_launchpad::board::clocks(),
@@ -67,8 +64,3 @@ pub fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
         delay.delay_ms(500u32);
     }
 }
-
-#[alloc_error_handler]
-fn oom(_: Layout) -> ! {
-    board::panic();
-}
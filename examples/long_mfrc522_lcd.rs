@@ -1,6 +1,5 @@
 #![no_std]
 #![no_main]
-#![feature(alloc_error_handler)]
 
 extern crate embedded_hal;
 extern crate hd44780_driver;
@@ -9,7 +8,6 @@ extern crate numtoa;
 extern crate stellaris_launchpad;
 extern crate tm4c123x_hal;
 
-use core::alloc::Layout;
 use embedded_hal::blocking::delay::DelayMs;
 use embedded_hal::digital::v1_compat::OldOutputPin;
 use embedded_hal::digital::v2::OutputPin;
@@ -21,8 +19,8 @@ use tm4c123x_hal::gpio::GpioExt;
 
 const MASTER_CARD: [u8; 4] = [192, 33, 232, 239];
 
-#[no_mangle]
-pub fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
+#[stellaris_launchpad::main]
+fn main(mut board: stellaris_launchpad::board::Board) {
     let mut delay = tm4c123x_hal::delay::Delay::new(
         board.core_peripherals.SYST,
         stellaris_launchpad::board::clocks(),
@@ -108,8 +106,3 @@ pub fn stellaris_main(mut board: stellaris_launchpad::board::Board) {
         }
     }
 }
-
-#[alloc_error_handler]
-fn oom(_: Layout) -> ! {
-    board::panic();
-}
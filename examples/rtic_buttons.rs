@@ -0,0 +1,63 @@
+//! The board under RTIC: SW1 toggles the red LED, SW2 the blue one, and a
+//! software timer pulses the green one as a heartbeat - each from its own
+//! task, sharing the LED as an RTIC resource.
+//!
+//! Build with `--features rtic`, which leaves the entry point and interrupt
+//! handlers to RTIC.
+
+#![no_std]
+#![no_main]
+
+#[rtic::app(device = tm4c123x_hal::tm4c123x, peripherals = true, dispatchers = [TIMER4A])]
+mod app {
+    use stellaris_launchpad::board::edges::{self, Trigger};
+    use stellaris_launchpad::board::time::{self, Duration};
+    use stellaris_launchpad::board::{timers, Board, Button, Led, RgbLed};
+
+    #[shared]
+    struct Shared {
+        led: RgbLed,
+    }
+
+    #[local]
+    struct Local {}
+
+    #[init]
+    fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+        let board = Board::from_peripherals(cx.core, cx.device);
+        let led = RgbLed::new(board.led_red, board.led_green, board.led_blue);
+        edges::listen(Button::One, Trigger::Press);
+        edges::listen(Button::Two, Trigger::Press);
+        time::init(board.WTIMER5, &board.power_control);
+        timers::start(board.TIMER3, &board.power_control);
+        timers::schedule_periodic(Duration::from_millis(1000), |_| {
+            let _ = heartbeat::spawn();
+        })
+        .unwrap();
+        (Shared { led }, Local {}, init::Monotonics())
+    }
+
+    /// The crate's switch interrupt handling, then ours.
+    #[task(binds = GPIOF, shared = [led])]
+    fn buttons(mut cx: buttons::Context) {
+        edges::on_interrupt();
+        while let Some(button) = edges::take_pending() {
+            let colour = match button {
+                Button::One => Led::Red,
+                Button::Two => Led::Blue,
+            };
+            cx.shared.led.lock(|led| led.toggle(colour));
+        }
+    }
+
+    /// The software timer service needs its interrupt forwarded.
+    #[task(binds = TIMER3A)]
+    fn timer(_cx: timer::Context) {
+        timers::on_interrupt();
+    }
+
+    #[task(shared = [led])]
+    fn heartbeat(mut cx: heartbeat::Context) {
+        cx.shared.led.lock(|led| led.toggle(Led::Green));
+    }
+}
@@ -0,0 +1,136 @@
+//! A command shell on the console. Connect at 115200 bps and try `help`,
+//! `led red on`, `gpio read pf4`, `adc read 3`, `uptime` or `heap`.
+
+#![no_std]
+#![no_main]
+
+extern crate stellaris_launchpad;
+extern crate tm4c123x_hal;
+
+use core::fmt::Write;
+use stellaris_launchpad::board::{commands, console, time, Led, RgbLed};
+use stellaris_launchpad::shell::{Command, Shell};
+use tm4c123x_hal::sysctl::{self, Domain, PowerState, RunMode};
+use tm4c123x_hal::tm4c123x::{ADC0, GPIO_PORTE};
+
+/// What the application's commands work on.
+struct App {
+    led: RgbLed,
+    adc: ADC0,
+}
+
+// AIN0 to AIN3 are PE3 to PE0.
+const ANALOG_PINS: u32 = 0x0F;
+// Sample sequencer 3 takes one sample.
+const SS3: u32 = 1 << 3;
+// SSCTL3: interrupt flag and end of sequence after the first sample.
+const SSCTL_IE0_END0: u32 = 0x6;
+
+#[stellaris_launchpad::main]
+fn main(board: stellaris_launchpad::board::Board) {
+    let parts = board.split();
+    parts.console.init(&parts.power_control);
+    time::init(parts.timers.wtimer5, &parts.power_control);
+
+    sysctl::control_power(
+        &parts.power_control,
+        Domain::GpioE,
+        RunMode::Run,
+        PowerState::On,
+    );
+    sysctl::control_power(
+        &parts.power_control,
+        Domain::Adc0,
+        RunMode::Run,
+        PowerState::On,
+    );
+    setup_adc(&parts.gpio.porte, &parts.adc.adc0);
+
+    let mut app = App {
+        led: parts.leds.into_rgb_led(),
+        adc: parts.adc.adc0,
+    };
+
+    let mut shell = Shell::new("launchpad> ");
+    commands::register(&mut shell).unwrap();
+    shell
+        .register(
+            Command::new("led", "led red|green|blue on|off|toggle", led)
+                .words(&["red", "green", "blue", "on", "off", "toggle"]),
+        )
+        .unwrap();
+    shell
+        .register(Command::new("adc", "adc read <0-3>", adc).words(&["read"]))
+        .unwrap();
+
+    shell.start(&mut console::Output).unwrap();
+    loop {
+        console::poll(&mut shell, &mut app);
+    }
+}
+
+fn led(app: &mut App, args: &[&str], _out: &mut dyn Write) -> Result<(), &'static str> {
+    let (colour, action) = match args {
+        [colour, action] => (*colour, *action),
+        _ => return Err("usage: led red|green|blue on|off|toggle"),
+    };
+    let led = match colour {
+        "red" => Led::Red,
+        "green" => Led::Green,
+        "blue" => Led::Blue,
+        _ => return Err("the colours are red, green and blue"),
+    };
+    match action {
+        "on" => app.led.set(led, true),
+        "off" => app.led.set(led, false),
+        "toggle" => app.led.toggle(led),
+        _ => return Err("the LED can go on, off or toggle"),
+    }
+    Ok(())
+}
+
+fn adc(app: &mut App, args: &[&str], out: &mut dyn Write) -> Result<(), &'static str> {
+    let channel = match args {
+        ["read", channel] => channel.parse::<u32>().ok().filter(|&c| c < 4),
+        _ => return Err("usage: adc read <0-3>"),
+    };
+    let channel = channel.ok_or("the channels are 0 to 3")?;
+    let adc = &app.adc;
+    adc.ssmux3.write(|w| unsafe { w.bits(channel) });
+    adc.pssi.write(|w| unsafe { w.bits(SS3) });
+    while adc.ris.read().bits() & SS3 == 0 {}
+    let sample = adc.ssfifo3.read().bits() & 0xFFF;
+    adc.isc.write(|w| unsafe { w.bits(SS3) });
+    let _ = writeln!(
+        out,
+        "AIN{} = {} ({} mV)",
+        channel,
+        sample,
+        sample * 3300 / 4095
+    );
+    Ok(())
+}
+
+/// Make PE0 to PE3 analogue inputs, and set ADC0's sequencer 3 up to take
+/// a single sample when asked.
+fn setup_adc(porte: &GPIO_PORTE, adc: &ADC0) {
+    porte
+        .dir
+        .modify(|r, w| unsafe { w.bits(r.bits() & !ANALOG_PINS) });
+    porte
+        .afsel
+        .modify(|r, w| unsafe { w.bits(r.bits() | ANALOG_PINS) });
+    porte
+        .den
+        .modify(|r, w| unsafe { w.bits(r.bits() & !ANALOG_PINS) });
+    porte
+        .amsel
+        .modify(|r, w| unsafe { w.bits(r.bits() | ANALOG_PINS) });
+
+    adc.actss.modify(|r, w| unsafe { w.bits(r.bits() & !SS3) });
+    // Triggered by the processor
+    adc.emux
+        .modify(|r, w| unsafe { w.bits(r.bits() & !0xF000) });
+    adc.ssctl3.write(|w| unsafe { w.bits(SSCTL_IE0_END0) });
+    adc.actss.modify(|r, w| unsafe { w.bits(r.bits() | SS3) });
+}
@@ -0,0 +1,117 @@
+//! Telemetry over UART0 (the USB cable) using the framed binary protocol,
+//! instead of text: a `Hello` at start-up, a `Heartbeat` every second (or as
+//! often as the PC asks), and answers to the PC's commands. Read it with
+//! the `stellaris-launchpad-telemetry-host` crate.
+
+#![no_std]
+#![no_main]
+
+extern crate cortex_m;
+extern crate stellaris_launchpad;
+extern crate tm4c123x_hal;
+
+use cortex_m::peripheral::SCB;
+use stellaris_launchpad::board::link::Link;
+use stellaris_launchpad::board::time::{self, Duration, Timeout};
+use stellaris_launchpad::board::uart::BufferedUart;
+use stellaris_launchpad::board::{Board, Led, ResetCause, RgbLed};
+use stellaris_launchpad::telemetry::{Command, Telemetry};
+use tm4c123x_hal::gpio::GpioExt;
+use tm4c123x_hal::serial;
+use tm4c123x_hal::time::Bps;
+
+#[stellaris_launchpad::main]
+fn main(board: Board) {
+    let reset_cause = describe(board.reset_cause());
+    time::init(board.WTIMER5, &board.power_control);
+    let mut pins_a = board.GPIO_PORTA.split(&board.power_control);
+    let serial = serial::Serial::uart0(
+        board.UART0,
+        pins_a.pa1.into_af_push_pull(&mut pins_a.control),
+        pins_a.pa0.into_af_push_pull(&mut pins_a.control),
+        (),
+        (),
+        Bps(115200),
+        // Frames are binary, so leave them alone.
+        serial::NewlineMode::Binary,
+        stellaris_launchpad::board::clocks(),
+        &board.power_control,
+    );
+    let rx = cortex_m::singleton!(: [u8; 128] = [0; 128]).unwrap();
+    let tx = cortex_m::singleton!(: [u8; 512] = [0; 512]).unwrap();
+    let uart = BufferedUart::new(serial, rx, tx);
+    let mut link = Link::new(uart);
+    let mut led = RgbLed::new(board.led_red, board.led_green, board.led_blue);
+
+    let _ = link.send(&Telemetry::Hello {
+        firmware: concat!("stellaris-launchpad ", env!("CARGO_PKG_VERSION")),
+        reset_cause,
+    });
+
+    let mut period = Duration::from_millis(1000);
+    let mut heartbeat = Timeout::after(period);
+    let mut loops: u32 = 0;
+    loop {
+        loops = loops.wrapping_add(1);
+        while let Some(command) = link.poll() {
+            let answer = match command {
+                Ok(Command::Ping { sequence }) => Telemetry::Pong { sequence },
+                Ok(Command::SetLed {
+                    sequence,
+                    red,
+                    green,
+                    blue,
+                }) => {
+                    led.set(Led::Red, red);
+                    led.set(Led::Green, green);
+                    led.set(Led::Blue, blue);
+                    Telemetry::Ack { sequence, ok: true }
+                }
+                Ok(Command::SetHeartbeat {
+                    sequence,
+                    period_ms,
+                }) => {
+                    period = Duration::from_millis(u64::from(period_ms));
+                    heartbeat = Timeout::after(period);
+                    Telemetry::Ack { sequence, ok: true }
+                }
+                Ok(Command::Reset { sequence }) => {
+                    let _ = link.send(&Telemetry::Ack { sequence, ok: true });
+                    link.free().flush();
+                    SCB::sys_reset();
+                }
+                Err(_) => Telemetry::Log {
+                    level: 1,
+                    module: module_path!(),
+                    text: "bad frame",
+                },
+            };
+            let _ = link.send(&answer);
+        }
+
+        if period != Duration::from_millis(0) && heartbeat.expired() {
+            heartbeat = Timeout::after(period);
+            let _ = link.send(&Telemetry::Heartbeat {
+                uptime_ms: time::now().since_boot().as_millis() as u64,
+                loops,
+            });
+        }
+    }
+}
+
+/// Sum up a reset cause for the `Hello` message.
+fn describe(cause: ResetCause) -> &'static str {
+    if cause.power_on() {
+        "power-on"
+    } else if cause.brown_out() {
+        "brown-out"
+    } else if cause.watchdog() {
+        "watchdog"
+    } else if cause.software() {
+        "software"
+    } else if cause.external() {
+        "reset pin"
+    } else {
+        "unknown"
+    }
+}
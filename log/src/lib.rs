@@ -0,0 +1,302 @@
+//! Log levels, per-module filtering and the line format of the
+//! stellaris-launchpad console, kept separate from the board support crate
+//! so they can be unit tested on the host:
+//!
+//! ```text
+//! cargo test -p stellaris-launchpad-log --target x86_64-unknown-linux-gnu
+//! ```
+
+#![no_std]
+#![deny(missing_docs)]
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::fmt;
+use core::time::Duration;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// How important a message is, from most to least.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    /// Something has gone wrong.
+    Error,
+    /// Something looks wrong.
+    Warn,
+    /// Something worth knowing.
+    Info,
+    /// Detail for debugging.
+    Debug,
+    /// Lots of detail for debugging.
+    Trace,
+}
+
+/// Which messages get through: a level for every module, plus up to
+/// `MAX_RULES` overrides for particular modules.
+///
+/// ```
+/// # use stellaris_launchpad_log::{Filter, Level};
+/// let filter = Filter::new(Level::Info)
+///     .module("app::radio", Some(Level::Trace))
+///     .module("app::radio::noisy", None);
+/// assert!(filter.enabled(Level::Trace, "app::radio::rx"));
+/// assert!(!filter.enabled(Level::Error, "app::radio::noisy"));
+/// assert!(!filter.enabled(Level::Debug, "app::main"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Filter {
+    default: Option<Level>,
+    rules: [Option<Rule>; MAX_RULES],
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The most per-module overrides a `Filter` can hold.
+pub const MAX_RULES: usize = 8;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rule {
+    module: &'static str,
+    level: Option<Level>,
+}
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Level {
+    /// The name of the level, in capitals, as it appears in a log line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl Filter {
+    /// Let through messages at `level` or more important, from every module.
+    pub const fn new(level: Level) -> Filter {
+        Filter {
+            default: Some(level),
+            rules: [None; MAX_RULES],
+        }
+    }
+
+    /// Let nothing through.
+    pub const fn off() -> Filter {
+        Filter {
+            default: None,
+            rules: [None; MAX_RULES],
+        }
+    }
+
+    /// Use `level` (or, with `None`, silence) for `module` and the modules
+    /// inside it, instead of the level for every module. Where rules
+    /// overlap, the longest module path wins. A second rule for the same
+    /// module replaces the first; a new rule beyond `MAX_RULES` is ignored.
+    pub fn module(mut self, module: &'static str, level: Option<Level>) -> Filter {
+        let slot = self
+            .rules
+            .iter()
+            .position(|r| r.is_some_and(|r| r.module == module))
+            .or_else(|| self.rules.iter().position(Option::is_none));
+        if let Some(slot) = slot {
+            self.rules[slot] = Some(Rule { module, level });
+        }
+        self
+    }
+
+    /// Would a message at `level`, from the module `module_path`, get
+    /// through?
+    pub fn enabled(&self, level: Level, module_path: &str) -> bool {
+        let mut best: Option<&Rule> = None;
+        for rule in self.rules.iter().flatten() {
+            let longer = best.is_none_or(|b| rule.module.len() > b.module.len());
+            if longer && contains(rule.module, module_path) {
+                best = Some(rule);
+            }
+        }
+        let max = best.map_or(self.default, |rule| rule.level);
+        max.is_some_and(|max| level <= max)
+    }
+}
+
+impl Default for Filter {
+    /// `Info` and above, from everywhere.
+    fn default() -> Filter {
+        Filter::new(Level::Info)
+    }
+}
+
+/// Write one log line, with a newline:
+///
+/// ```text
+/// [    1.234567] INFO  app::radio: tuned to 868.1 MHz
+/// ```
+///
+/// The timestamp (seconds since boot) is left out if there isn't one.
+pub fn write_record<W: fmt::Write>(
+    w: &mut W,
+    timestamp: Option<Duration>,
+    level: Level,
+    module_path: &str,
+    args: fmt::Arguments,
+) -> fmt::Result {
+    if let Some(t) = timestamp {
+        write!(w, "[{:5}.{:06}] ", t.as_secs(), t.subsec_micros())?;
+    }
+    writeln!(w, "{:<5} {}: {}", level, module_path, args)
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Is `path` the module `module`, or inside it?
+fn contains(module: &str, path: &str) -> bool {
+    path.starts_with(module)
+        && (path.len() == module.len() || path[module.len()..].starts_with("::"))
+}
+
+// ****************************************************************************
+//
+// Tests
+//
+// ****************************************************************************
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::string::String;
+
+    fn record(timestamp: Option<Duration>, level: Level, args: fmt::Arguments) -> String {
+        let mut s = String::new();
+        write_record(&mut s, timestamp, level, "app::radio", args).unwrap();
+        s
+    }
+
+    #[test]
+    fn levels_order_by_importance() {
+        assert!(Level::Error < Level::Warn);
+        assert!(Level::Debug < Level::Trace);
+    }
+
+    #[test]
+    fn default_filter_is_info() {
+        let filter = Filter::default();
+        assert!(filter.enabled(Level::Error, "app"));
+        assert!(filter.enabled(Level::Info, "app"));
+        assert!(!filter.enabled(Level::Debug, "app"));
+    }
+
+    #[test]
+    fn off_lets_nothing_through() {
+        assert!(!Filter::off().enabled(Level::Error, "app"));
+    }
+
+    #[test]
+    fn module_rules_cover_children() {
+        let filter = Filter::off().module("app::radio", Some(Level::Debug));
+        assert!(filter.enabled(Level::Debug, "app::radio"));
+        assert!(filter.enabled(Level::Debug, "app::radio::rx"));
+        assert!(!filter.enabled(Level::Trace, "app::radio::rx"));
+        assert!(!filter.enabled(Level::Error, "app::radios"));
+        assert!(!filter.enabled(Level::Error, "app"));
+    }
+
+    #[test]
+    fn longest_rule_wins() {
+        let filter = Filter::new(Level::Warn)
+            .module("app::radio::rx", None)
+            .module("app::radio", Some(Level::Trace));
+        assert!(filter.enabled(Level::Trace, "app::radio::tx"));
+        assert!(!filter.enabled(Level::Error, "app::radio::rx::fifo"));
+        assert!(!filter.enabled(Level::Info, "app::main"));
+    }
+
+    #[test]
+    fn rules_replace_and_run_out() {
+        let filter = Filter::off()
+            .module("a", Some(Level::Error))
+            .module("a", Some(Level::Trace));
+        assert!(filter.enabled(Level::Trace, "a"));
+
+        let names = ["b0", "b1", "b2", "b3", "b4", "b5", "b6", "b7", "b8"];
+        let full = names
+            .iter()
+            .fold(Filter::off(), |f, name| f.module(name, Some(Level::Info)));
+        assert!(full.enabled(Level::Info, "b7"));
+        assert!(!full.enabled(Level::Info, "b8"));
+    }
+
+    #[test]
+    fn record_with_timestamp() {
+        let line = record(
+            Some(Duration::from_micros(1_234_567)),
+            Level::Info,
+            format_args!("tuned to {} MHz", 868.1),
+        );
+        assert_eq!(
+            line,
+            "[    1.234567] INFO  app::radio: tuned to 868.1 MHz\n"
+        );
+    }
+
+    #[test]
+    fn record_without_timestamp() {
+        let line = record(None, Level::Error, format_args!("no ack"));
+        assert_eq!(line, "ERROR app::radio: no ack\n");
+    }
+
+    #[test]
+    fn levels_are_padded() {
+        let line = record(None, Level::Warn, format_args!("x"));
+        assert_eq!(line, "WARN  app::radio: x\n");
+        let line = record(
+            Some(Duration::from_secs(12345)),
+            Level::Trace,
+            format_args!("x"),
+        );
+        assert_eq!(line, "[12345.000000] TRACE app::radio: x\n");
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,208 @@
+//! Attribute macros for the `stellaris-launchpad` crate. You want
+//! `stellaris_launchpad::main`, which re-exports what's in here.
+
+extern crate proc_macro;
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, FnArg, ItemFn, ReturnType, Type};
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// The arguments to `#[main]` - currently just an optional
+/// `config = path::to::function`.
+struct MainArgs {
+    config: Option<syn::Path>,
+}
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Marks the application's entry point, which is called once the board has
+/// been initialised.
+///
+/// The function must take the `Board` by value and return either `!`,
+/// `()` or `Result<(), E>` where `E: Debug`. If it returns, `()` and `Ok(())`
+/// park the CPU, while an `Err` is reported over UART0 before halting.
+///
+/// ```ignore
+/// #[stellaris_launchpad::main(config = my_config)]
+/// fn main(board: stellaris_launchpad::board::Board) -> Result<(), MyError> {
+///     ...
+/// }
+///
+/// fn my_config() -> stellaris_launchpad::board::BoardConfig {
+///     stellaris_launchpad::board::BoardConfig::new().run_from_internal_oscillator()
+/// }
+/// ```
+///
+/// The optional `config` argument names a function returning the
+/// `BoardConfig` to start up with. Without it, the board starts with
+/// `BoardConfig::default()`.
+///
+/// The function may also be an `async fn` returning `()` or
+/// `Result<(), E>`, in which case it is run with
+/// `stellaris_launchpad::common::executor::block_on`, sleeping in `wfi`
+/// whenever it's waiting.
+///
+/// It can't be used with the crate's `rtic` feature, which leaves the entry
+/// point to the application (or RTIC).
+#[proc_macro_attribute]
+pub fn main(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as MainArgs);
+    let f = parse_macro_input!(input as ItemFn);
+
+    if cfg!(feature = "rtic") {
+        return syn::Error::new(
+            f.sig.ident.span(),
+            "`#[stellaris_launchpad::main]` can't be used with the `rtic` feature, \
+             which leaves the entry point to the application",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    if let Err(e) = check_signature(&f) {
+        return e.to_compile_error().into();
+    }
+
+    let ident = &f.sig.ident;
+    let call = match f.sig.output {
+        _ if f.sig.asyncness.is_some() => quote! {
+            ::stellaris_launchpad::common::startup::MainReturn::exit(
+                ::stellaris_launchpad::common::executor::block_on(#ident(board)),
+            )
+        },
+        ReturnType::Type(_, ref ty) if is_never(ty) => quote! {
+            let main: fn(::stellaris_launchpad::board::Board) -> ! = #ident;
+            main(board)
+        },
+        ReturnType::Type(_, ref ty) => quote! {
+            let main: fn(::stellaris_launchpad::board::Board) -> #ty = #ident;
+            ::stellaris_launchpad::common::startup::MainReturn::exit(main(board))
+        },
+        ReturnType::Default => quote! {
+            let main: fn(::stellaris_launchpad::board::Board) = #ident;
+            ::stellaris_launchpad::common::startup::MainReturn::exit(main(board))
+        },
+    };
+
+    let config = match args.config {
+        Some(path) => quote! {
+            let config: fn() -> ::stellaris_launchpad::board::BoardConfig = #path;
+            config()
+        },
+        None => quote! {
+            ::stellaris_launchpad::board::BoardConfig::default()
+        },
+    };
+
+    quote!(
+        #f
+
+        #[doc(hidden)]
+        #[export_name = "stellaris_main"]
+        pub fn __stellaris_launchpad_main() -> ! {
+            let board = ::stellaris_launchpad::common::startup::init_board({ #config });
+            #call
+        }
+    )
+    .into()
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl Parse for MainArgs {
+    fn parse(input: ParseStream) -> syn::Result<MainArgs> {
+        if input.is_empty() {
+            return Ok(MainArgs { config: None });
+        }
+        let key: syn::Ident = input.parse()?;
+        if key != "config" {
+            return Err(syn::Error::new(key.span(), "expected `config = <path>`"));
+        }
+        input.parse::<syn::Token![=]>()?;
+        let config = input.parse()?;
+        if !input.is_empty() {
+            return Err(input.error("unexpected tokens after `config = <path>`"));
+        }
+        Ok(MainArgs {
+            config: Some(config),
+        })
+    }
+}
+
+/// Reject anything we can't call as `fn(Board) -> R`. The argument and
+/// return types themselves are checked by the compiler when the generated
+/// code coerces the function to a function pointer.
+fn check_signature(f: &ItemFn) -> syn::Result<()> {
+    let sig = &f.sig;
+    if let Some(ref t) = sig.constness {
+        return cannot_be(t, "`const`");
+    }
+    if let Some(ref t) = sig.unsafety {
+        return cannot_be(t, "`unsafe`");
+    }
+    if let Some(ref t) = sig.abi {
+        return cannot_be(t, "`extern`");
+    }
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        return cannot_be(&sig.generics, "generic");
+    }
+    if let Some(ref t) = sig.variadic {
+        return cannot_be(t, "variadic");
+    }
+    let mut inputs = sig.inputs.iter();
+    match (inputs.next(), inputs.next()) {
+        (Some(FnArg::Typed(_)), None) => Ok(()),
+        (Some(FnArg::Receiver(r)), _) => cannot_be(r, "a method"),
+        (Some(_), Some(extra)) => Err(syn::Error::new_spanned(
+            extra,
+            "the entry point must take exactly one argument, the `Board`",
+        )),
+        (None, _) => Err(syn::Error::new(
+            Span::call_site(),
+            "the entry point must take the `Board` as its argument",
+        )),
+    }
+}
+
+fn cannot_be<T: ToTokens>(tokens: T, what: &str) -> syn::Result<()> {
+    Err(syn::Error::new_spanned(
+        tokens,
+        format!("the entry point cannot be {}", what),
+    ))
+}
+
+fn is_never(ty: &Type) -> bool {
+    match *ty {
+        Type::Never(_) => true,
+        Type::Paren(ref p) => is_never(&p.elem),
+        _ => false,
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
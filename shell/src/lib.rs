@@ -0,0 +1,767 @@
+//! A line-editing command shell for the stellaris-launchpad console. It
+//! knows nothing about UARTs: feed it the bytes that arrive and give it
+//! somewhere to write, so it can be unit tested on the host:
+//!
+//! ```text
+//! cargo test -p stellaris-launchpad-shell --target x86_64-unknown-linux-gnu
+//! ```
+//!
+//! Commands are plain functions, registered by name, which get the
+//! arguments and a context of the application's choosing (the `Board`,
+//! say):
+//!
+//! ```
+//! # use core::fmt::Write;
+//! # use stellaris_launchpad_shell::{Command, Shell};
+//! struct Leds {
+//!     red: bool,
+//! }
+//!
+//! fn led(leds: &mut Leds, args: &[&str], _out: &mut dyn Write) -> Result<(), &'static str> {
+//!     match args {
+//!         ["red", "on"] => leds.red = true,
+//!         ["red", "off"] => leds.red = false,
+//!         _ => return Err("usage: led red on|off"),
+//!     }
+//!     Ok(())
+//! }
+//!
+//! let mut shell = Shell::new("> ");
+//! shell
+//!     .register(Command::new("led", "led red on|off", led).words(&["red", "on", "off"]))
+//!     .unwrap();
+//! let mut leds = Leds { red: false };
+//! let mut out = String::new();
+//! for &byte in b"led red on\r" {
+//!     shell.feed(byte, &mut leds, &mut out).unwrap();
+//! }
+//! assert!(leds.red);
+//! ```
+//!
+//! The editor understands backspace, Ctrl-C (abandon the line), Ctrl-U
+//! (clear the line), Tab (complete a command name, or one of the command's
+//! `words`) and the up and down arrows (history). There's a built-in `help`
+//! command, unless you register your own.
+
+#![no_std]
+#![deny(missing_docs)]
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::fmt::{self, Write};
+use core::str;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// What a command does: given the context, the arguments after the
+/// command's name and somewhere to write, do it, or say what was wrong.
+pub type Handler<C> = fn(&mut C, &[&str], &mut dyn Write) -> Result<(), &'static str>;
+
+/// A command the shell can run.
+pub struct Command<C> {
+    /// What you type to run it
+    pub name: &'static str,
+    /// A line about it, for `help`
+    pub help: &'static str,
+    /// Words which Tab can complete as its arguments
+    pub words: &'static [&'static str],
+    /// The function which runs it
+    pub run: Handler<C>,
+}
+
+/// Why a command couldn't be registered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// There are already `MAX_COMMANDS` commands.
+    Full,
+    /// There's already a command with that name.
+    Duplicate,
+}
+
+/// A command shell. See the crate documentation.
+pub struct Shell<C> {
+    prompt: &'static str,
+    commands: [Option<Command<C>>; MAX_COMMANDS],
+    line: Line,
+    history: [Line; HISTORY],
+    history_len: usize,
+    history_next: usize,
+    /// How far back in the history we are, and the line we were typing
+    /// before we went there.
+    browsing: Option<(usize, Line)>,
+    escape: Escape,
+    last_was_cr: bool,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The longest line, in bytes.
+pub const MAX_LINE: usize = 80;
+
+/// How many lines the history keeps.
+pub const HISTORY: usize = 8;
+
+/// The most commands a shell can have.
+pub const MAX_COMMANDS: usize = 16;
+
+/// The most words (the name and its arguments) in a command line.
+pub const MAX_ARGS: usize = 8;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// A line of printable ASCII.
+#[derive(Clone, Copy)]
+struct Line {
+    bytes: [u8; MAX_LINE],
+    len: usize,
+}
+
+/// Where we are in a terminal escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    None,
+    /// Had ESC
+    Start,
+    /// Had ESC [ (or ESC O)
+    Sequence,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const BELL: &str = "\x07";
+const RUB_OUT: &str = "\x08 \x08";
+
+const CTRL_C: u8 = 0x03;
+const BACKSPACE: u8 = 0x08;
+const TAB: u8 = b'\t';
+const CTRL_U: u8 = 0x15;
+const ESC: u8 = 0x1B;
+const DELETE: u8 = 0x7F;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl<C> Command<C> {
+    /// A command with no completion words.
+    pub fn new(name: &'static str, help: &'static str, run: Handler<C>) -> Command<C> {
+        Command {
+            name,
+            help,
+            words: &[],
+            run,
+        }
+    }
+
+    /// Give Tab some words to complete arguments with.
+    pub fn words(mut self, words: &'static [&'static str]) -> Command<C> {
+        self.words = words;
+        self
+    }
+}
+
+impl<C> Clone for Command<C> {
+    fn clone(&self) -> Command<C> {
+        *self
+    }
+}
+
+impl<C> Copy for Command<C> {}
+
+impl<C> Shell<C> {
+    /// A shell with no commands (apart from `help`), which shows `prompt`.
+    pub fn new(prompt: &'static str) -> Shell<C> {
+        Shell {
+            prompt,
+            commands: [None; MAX_COMMANDS],
+            line: Line::EMPTY,
+            history: [Line::EMPTY; HISTORY],
+            history_len: 0,
+            history_next: 0,
+            browsing: None,
+            escape: Escape::None,
+            last_was_cr: false,
+        }
+    }
+
+    /// Add a command.
+    pub fn register(&mut self, command: Command<C>) -> Result<(), Error> {
+        if self.find(command.name).is_some() {
+            return Err(Error::Duplicate);
+        }
+        let slot = self
+            .commands
+            .iter_mut()
+            .find(|c| c.is_none())
+            .ok_or(Error::Full)?;
+        *slot = Some(command);
+        Ok(())
+    }
+
+    /// Show the prompt. Call once, when the terminal is ready.
+    pub fn start<W: Write>(&mut self, out: &mut W) -> fmt::Result {
+        out.write_str(self.prompt)
+    }
+
+    /// Deal with a byte typed at the terminal, echoing it and running the
+    /// command when a line is finished.
+    pub fn feed<W: Write>(&mut self, byte: u8, context: &mut C, out: &mut W) -> fmt::Result {
+        let last_was_cr = self.last_was_cr;
+        self.last_was_cr = byte == b'\r';
+        match self.escape {
+            Escape::Start => {
+                self.escape = match byte {
+                    b'[' | b'O' => Escape::Sequence,
+                    _ => Escape::None,
+                };
+                return Ok(());
+            }
+            Escape::Sequence => {
+                // Parameters and intermediates carry on; anything else ends it.
+                if (0x40..=0x7E).contains(&byte) {
+                    self.escape = Escape::None;
+                    match byte {
+                        b'A' => return self.older(out),
+                        b'B' => return self.newer(out),
+                        _ => {}
+                    }
+                }
+                return Ok(());
+            }
+            Escape::None => {}
+        }
+        match byte {
+            b'\n' if last_was_cr => Ok(()),
+            b'\r' | b'\n' => self.execute(context, out),
+            BACKSPACE | DELETE => {
+                if self.line.len > 0 {
+                    self.line.len -= 1;
+                    out.write_str(RUB_OUT)?;
+                }
+                Ok(())
+            }
+            CTRL_C => {
+                self.line = Line::EMPTY;
+                self.browsing = None;
+                out.write_str("^C\n")?;
+                out.write_str(self.prompt)
+            }
+            CTRL_U => self.replace_line(Line::EMPTY, out),
+            TAB => self.complete(out),
+            ESC => {
+                self.escape = Escape::Start;
+                Ok(())
+            }
+            0x20..=0x7E => {
+                if self.line.push_str(char_str(&byte)) {
+                    out.write_str(char_str(&byte))
+                } else {
+                    out.write_str(BELL)
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The line typed so far.
+    pub fn line(&self) -> &str {
+        self.line.as_str()
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl Line {
+    const EMPTY: Line = Line {
+        bytes: [0; MAX_LINE],
+        len: 0,
+    };
+
+    fn as_str(&self) -> &str {
+        // Only printable ASCII gets in.
+        str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+
+    /// Add `s` to the end, if it fits.
+    fn push_str(&mut self, s: &str) -> bool {
+        let end = self.len + s.len();
+        if end > MAX_LINE {
+            return false;
+        }
+        self.bytes[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        true
+    }
+}
+
+impl<C> Shell<C> {
+    fn find(&self, name: &str) -> Option<Command<C>> {
+        self.commands
+            .iter()
+            .flatten()
+            .find(|c| c.name == name)
+            .copied()
+    }
+
+    /// Run the line, and start a new one.
+    fn execute<W: Write>(&mut self, context: &mut C, out: &mut W) -> fmt::Result {
+        out.write_str("\n")?;
+        let line = self.line;
+        self.line = Line::EMPTY;
+        self.browsing = None;
+        let text = line.as_str();
+
+        let mut args = [""; MAX_ARGS];
+        let mut count = 0;
+        let mut words = text.split_whitespace();
+        for word in words.by_ref().take(MAX_ARGS) {
+            args[count] = word;
+            count += 1;
+        }
+        if count > 0 {
+            self.remember(&line);
+            if words.next().is_some() {
+                writeln!(out, "error: more than {} words", MAX_ARGS)?;
+            } else {
+                self.run(&args[..count], context, out)?;
+            }
+        }
+        out.write_str(self.prompt)
+    }
+
+    fn run<W: Write>(&self, args: &[&str], context: &mut C, out: &mut W) -> fmt::Result {
+        match self.find(args[0]) {
+            Some(command) => {
+                if let Err(message) = (command.run)(context, &args[1..], out) {
+                    writeln!(out, "error: {}", message)?;
+                }
+                Ok(())
+            }
+            None if args[0] == "help" => self.help(out),
+            None => writeln!(out, "unknown command '{}' - try 'help'", args[0]),
+        }
+    }
+
+    fn help<W: Write>(&self, out: &mut W) -> fmt::Result {
+        let width = self
+            .commands
+            .iter()
+            .flatten()
+            .map(|c| c.name.len())
+            .max()
+            .unwrap_or(0);
+        for command in self.commands.iter().flatten() {
+            writeln!(
+                out,
+                "{:width$}  {}",
+                command.name,
+                command.help,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Add a line to the history, unless it's the same as the last one.
+    fn remember(&mut self, line: &Line) {
+        if self.history_len > 0 && self.history_entry(0).as_str() == line.as_str() {
+            return;
+        }
+        self.history[self.history_next] = *line;
+        self.history_next = (self.history_next + 1) % HISTORY;
+        self.history_len = (self.history_len + 1).min(HISTORY);
+    }
+
+    /// The line `back` lines back (0 is the most recent).
+    fn history_entry(&self, back: usize) -> &Line {
+        &self.history[(self.history_next + HISTORY - 1 - back) % HISTORY]
+    }
+
+    /// Up arrow: the line before the one showing.
+    fn older<W: Write>(&mut self, out: &mut W) -> fmt::Result {
+        let (back, draft) = match self.browsing {
+            Some((back, draft)) => (back + 1, draft),
+            None => (0, self.line),
+        };
+        if back >= self.history_len {
+            return out.write_str(BELL);
+        }
+        self.browsing = Some((back, draft));
+        let line = *self.history_entry(back);
+        self.replace_line(line, out)
+    }
+
+    /// Down arrow: the line after the one showing, or back to what was
+    /// being typed.
+    fn newer<W: Write>(&mut self, out: &mut W) -> fmt::Result {
+        match self.browsing {
+            None => out.write_str(BELL),
+            Some((0, draft)) => {
+                self.browsing = None;
+                self.replace_line(draft, out)
+            }
+            Some((back, draft)) => {
+                self.browsing = Some((back - 1, draft));
+                let line = *self.history_entry(back - 1);
+                self.replace_line(line, out)
+            }
+        }
+    }
+
+    /// Rub out the line on the terminal and show `line` instead.
+    fn replace_line<W: Write>(&mut self, line: Line, out: &mut W) -> fmt::Result {
+        for _ in 0..self.line.len {
+            out.write_str(RUB_OUT)?;
+        }
+        self.line = line;
+        out.write_str(self.line.as_str())
+    }
+
+    /// Tab: finish the word being typed, as far as the candidates agree, or
+    /// list them if they don't.
+    fn complete<W: Write>(&mut self, out: &mut W) -> fmt::Result {
+        let line = self.line;
+        let text = line.as_str();
+        let start = text.rfind(' ').map_or(0, |i| i + 1);
+        let partial = &text[start..];
+        let mut earlier = text[..start].split_whitespace();
+        let command = earlier.next();
+
+        let mut first: Option<&'static str> = None;
+        let mut count = 0;
+        let mut common = 0;
+        self.candidates(command, |candidate| {
+            if candidate.starts_with(partial) {
+                count += 1;
+                match first {
+                    None => {
+                        first = Some(candidate);
+                        common = candidate.len();
+                    }
+                    Some(first) => common = common.min(shared_prefix(first, candidate)),
+                }
+            }
+        });
+
+        let first = match first {
+            Some(first) => first,
+            None => return out.write_str(BELL),
+        };
+        let addition = &first[partial.len()..common];
+        let unique = count == 1;
+        if addition.is_empty() && !unique {
+            out.write_str("\n")?;
+            let mut separator = "";
+            self.candidates(command, |candidate| {
+                if candidate.starts_with(partial) {
+                    let _ = write!(out, "{}{}", separator, candidate);
+                    separator = "  ";
+                }
+            });
+            out.write_str("\n")?;
+            out.write_str(self.prompt)?;
+            return out.write_str(text);
+        }
+        if !self.line.push_str(addition) {
+            return out.write_str(BELL);
+        }
+        out.write_str(addition)?;
+        if unique && self.line.push_str(" ") {
+            out.write_str(" ")?;
+        }
+        Ok(())
+    }
+
+    /// Call `f` with each word which could come next: a command name, or
+    /// one of `command`'s words.
+    fn candidates<F>(&self, command: Option<&str>, mut f: F)
+    where
+        F: FnMut(&'static str),
+    {
+        match command {
+            None => {
+                for command in self.commands.iter().flatten() {
+                    f(command.name);
+                }
+                if self.find("help").is_none() {
+                    f("help");
+                }
+            }
+            Some(name) => {
+                if let Some(command) = self.find(name) {
+                    for word in command.words {
+                        f(word);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// How many bytes `a` and `b` start with in common.
+fn shared_prefix(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count()
+}
+
+/// A printable ASCII byte as a string.
+fn char_str(byte: &u8) -> &str {
+    str::from_utf8(core::slice::from_ref(byte)).unwrap_or("")
+}
+
+// ****************************************************************************
+//
+// Tests
+//
+// ****************************************************************************
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::string::String;
+    use std::vec::Vec;
+
+    #[derive(Default)]
+    struct Board {
+        red: bool,
+        ran: Vec<String>,
+    }
+
+    fn led(board: &mut Board, args: &[&str], _out: &mut dyn Write) -> Result<(), &'static str> {
+        match args {
+            ["red", "on"] => board.red = true,
+            ["red", "off"] => board.red = false,
+            _ => return Err("usage: led red on|off"),
+        }
+        Ok(())
+    }
+
+    fn echo(board: &mut Board, args: &[&str], out: &mut dyn Write) -> Result<(), &'static str> {
+        board.ran.push(args.join(" "));
+        writeln!(out, "{}", args.join(" ")).map_err(|_| "write failed")
+    }
+
+    fn gpio(_board: &mut Board, _args: &[&str], _out: &mut dyn Write) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn shell() -> Shell<Board> {
+        let mut shell = Shell::new("> ");
+        shell
+            .register(
+                Command::new("led", "led red on|off", led).words(&["red", "green", "on", "off"]),
+            )
+            .unwrap();
+        shell
+            .register(Command::new("echo", "echo words", echo))
+            .unwrap();
+        shell
+            .register(Command::new("gpio", "gpio read pin", gpio).words(&["read", "write"]))
+            .unwrap();
+        shell
+    }
+
+    fn feed(shell: &mut Shell<Board>, board: &mut Board, input: &[u8]) -> String {
+        let mut out = String::new();
+        for &byte in input {
+            shell.feed(byte, board, &mut out).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn runs_commands() {
+        let mut shell = shell();
+        let mut board = Board::default();
+        let out = feed(&mut shell, &mut board, b"led red on\r\n");
+        assert!(board.red);
+        assert_eq!(out, "led red on\n> ");
+        feed(&mut shell, &mut board, b"  led   red  off \n");
+        assert!(!board.red);
+    }
+
+    #[test]
+    fn reports_errors() {
+        let mut shell = shell();
+        let mut board = Board::default();
+        let out = feed(&mut shell, &mut board, b"led blue\r");
+        assert_eq!(out, "led blue\nerror: usage: led red on|off\n> ");
+        let out = feed(&mut shell, &mut board, b"frob\r");
+        assert_eq!(out, "frob\nunknown command 'frob' - try 'help'\n> ");
+        let out = feed(&mut shell, &mut board, b"echo 1 2 3 4 5 6 7 8\r");
+        assert_eq!(out, "echo 1 2 3 4 5 6 7 8\nerror: more than 8 words\n> ");
+        assert!(board.ran.is_empty());
+    }
+
+    #[test]
+    fn empty_lines_just_prompt() {
+        let mut shell = shell();
+        let mut board = Board::default();
+        assert_eq!(feed(&mut shell, &mut board, b"\r\n  \r"), "\n>   \n> ");
+        assert_eq!(feed(&mut shell, &mut board, b"\x1b[A"), "\x07");
+    }
+
+    #[test]
+    fn help_lists_commands() {
+        let mut shell = shell();
+        let mut board = Board::default();
+        let out = feed(&mut shell, &mut board, b"help\r");
+        assert_eq!(
+            out,
+            "help\nled   led red on|off\necho  echo words\ngpio  gpio read pin\n> "
+        );
+    }
+
+    #[test]
+    fn rejects_duplicates_and_overflow() {
+        let mut shell = shell();
+        assert_eq!(
+            shell.register(Command::new("led", "", led)),
+            Err(Error::Duplicate)
+        );
+        const NAMES: [&str; 13] = [
+            "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m",
+        ];
+        for &name in NAMES.iter() {
+            shell.register(Command::new(name, "", led)).unwrap();
+        }
+        assert_eq!(shell.register(Command::new("n", "", led)), Err(Error::Full));
+    }
+
+    #[test]
+    fn edits_the_line() {
+        let mut shell = shell();
+        let mut board = Board::default();
+        let out = feed(&mut shell, &mut board, b"echo hellp\x08o\r");
+        assert_eq!(out, "echo hellp\x08 \x08o\nhello\n> ");
+        let out = feed(&mut shell, &mut board, b"\x7f");
+        assert_eq!(out, "");
+        let out = feed(&mut shell, &mut board, b"led\x15echo x\x03");
+        assert_eq!(out, "led\x08 \x08\x08 \x08\x08 \x08echo x^C\n> ");
+        assert_eq!(shell.line(), "");
+        assert_eq!(board.ran, ["hello"]);
+    }
+
+    #[test]
+    fn lines_have_a_limit() {
+        let mut shell = shell();
+        let mut board = Board::default();
+        let long = [b'x'; MAX_LINE + 1];
+        let out = feed(&mut shell, &mut board, &long);
+        assert!(out.ends_with("x\x07"));
+        assert_eq!(shell.line().len(), MAX_LINE);
+    }
+
+    #[test]
+    fn completes_command_names() {
+        let mut shell = shell();
+        let mut board = Board::default();
+        assert_eq!(feed(&mut shell, &mut board, b"l\t"), "led ");
+        assert_eq!(shell.line(), "led ");
+        let mut shell = self::shell();
+        assert_eq!(feed(&mut shell, &mut board, b"he\t"), "help ");
+        let mut shell = self::shell();
+        assert_eq!(feed(&mut shell, &mut board, b"z\t"), "z\x07");
+    }
+
+    #[test]
+    fn completes_arguments() {
+        let mut shell = shell();
+        let mut board = Board::default();
+        feed(&mut shell, &mut board, b"led r\to\t");
+        assert_eq!(shell.line(), "led red o");
+        let out = feed(&mut shell, &mut board, b"\t");
+        assert_eq!(out, "\non  off\n> led red o");
+        feed(&mut shell, &mut board, b"n\r");
+        assert!(board.red);
+        assert_eq!(feed(&mut shell, &mut board, b"echo \t"), "echo \x07");
+    }
+
+    #[test]
+    fn completes_common_prefixes() {
+        let mut shell = shell();
+        shell.register(Command::new("ledger", "", led)).unwrap();
+        let mut board = Board::default();
+        assert_eq!(feed(&mut shell, &mut board, b"le\t"), "led");
+        assert_eq!(feed(&mut shell, &mut board, b"\t"), "\nled  ledger\n> led");
+        assert_eq!(feed(&mut shell, &mut board, b"g\t"), "ger ");
+    }
+
+    #[test]
+    fn recalls_history() {
+        let mut shell = shell();
+        let mut board = Board::default();
+        feed(&mut shell, &mut board, b"echo one\recho two\recho two\rech");
+        let out = feed(&mut shell, &mut board, b"\x1b[A");
+        assert_eq!(out, "\x08 \x08\x08 \x08\x08 \x08echo two");
+        feed(&mut shell, &mut board, b"\x1b[A");
+        assert_eq!(shell.line(), "echo one");
+        assert_eq!(feed(&mut shell, &mut board, b"\x1b[A"), "\x07");
+        feed(&mut shell, &mut board, b"\x1b[B");
+        assert_eq!(shell.line(), "echo two");
+        feed(&mut shell, &mut board, b"\x1bOB");
+        assert_eq!(shell.line(), "ech");
+        assert_eq!(feed(&mut shell, &mut board, b"\x1b[B"), "\x07");
+        feed(&mut shell, &mut board, b"\x1b[A\x1b[A!\r");
+        assert_eq!(board.ran, ["one", "two", "two", "one!"]);
+    }
+
+    #[test]
+    fn history_forgets_the_oldest() {
+        let mut shell = shell();
+        let mut board = Board::default();
+        for i in 0..HISTORY + 2 {
+            feed(
+                &mut shell,
+                &mut board,
+                std::format!("echo {}\r", i).as_bytes(),
+            );
+        }
+        for _ in 0..HISTORY {
+            feed(&mut shell, &mut board, b"\x1b[A");
+        }
+        assert_eq!(shell.line(), "echo 2");
+        assert_eq!(feed(&mut shell, &mut board, b"\x1b[A"), "\x07");
+    }
+
+    #[test]
+    fn ignores_other_escapes() {
+        let mut shell = shell();
+        let mut board = Board::default();
+        assert_eq!(feed(&mut shell, &mut board, b"ab\x1b[1;5Cc\x1b[3~"), "abc");
+        assert_eq!(shell.line(), "abc");
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,283 @@
+//! Debounced events from the Launchpad's two switches, SW1 (PF4) and SW2
+//! (PF0).
+//!
+//! `Buttons` works from samples: call `poll` with the time in milliseconds
+//! (any free-running counter will do - only differences are used) every few
+//! milliseconds, then drain the events with `next_event`. Rather than
+//! polling all the time, you can call `poll` when a switch's GPIO edge
+//! interrupt fires (see `board::edges`, with `Trigger::Both`), and then keep
+//! calling it only while `needs_polling` says a debounce or long-press timer
+//! is running.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use embedded_hal::digital::v2::InputPin;
+use tm4c123x_hal::gpio::gpiof::{PF0, PF4};
+use tm4c123x_hal::gpio::{Input, PullUp};
+
+use super::Button;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Something a switch (or both switches) did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonEvent {
+    /// The switch went down.
+    Pressed(Button),
+    /// The switch came back up.
+    Released(Button),
+    /// The switch has been held down for `ButtonTiming::long_press_ms`.
+    /// Sent once per press.
+    LongPress(Button),
+    /// The switch was pressed again within `ButtonTiming::double_click_ms`
+    /// of a short press being released. Sent after the second `Pressed`.
+    DoubleClick(Button),
+    /// Both switches went down within `ButtonTiming::chord_ms` of each
+    /// other. Sent after the second `Pressed`, once until both are released.
+    Chord,
+}
+
+/// How `Buttons` tells presses apart. All in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ButtonTiming {
+    /// How long a switch must read the same before we believe it.
+    pub debounce_ms: u32,
+    /// How long a switch must be held for a `LongPress`.
+    pub long_press_ms: u32,
+    /// The longest gap between a release and the next press that counts as
+    /// a `DoubleClick`.
+    pub double_click_ms: u32,
+    /// The longest gap between the two switches going down that counts as a
+    /// `Chord`.
+    pub chord_ms: u32,
+}
+
+/// The two switches, debounced.
+pub struct Buttons {
+    one: PF4<Input<PullUp>>,
+    two: PF0<Input<PullUp>>,
+    timing: ButtonTiming,
+    states: [SwitchState; 2],
+    chorded: bool,
+    events: [Option<ButtonEvent>; QUEUE_LEN],
+    head: usize,
+    len: usize,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// Where one switch has got to.
+#[derive(Clone, Copy)]
+struct SwitchState {
+    /// The last raw reading.
+    raw: bool,
+    /// When the raw reading last changed.
+    raw_since: u32,
+    /// The debounced state.
+    pressed: bool,
+    /// When the debounced state last went down.
+    pressed_at: u32,
+    /// Have we sent `LongPress` for this press?
+    long_sent: bool,
+    /// When the last short press was released, if it's recent enough to be
+    /// the first half of a double click.
+    clicked_at: Option<u32>,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// Events beyond this many, not yet collected, are dropped.
+const QUEUE_LEN: usize = 8;
+
+const BUTTONS: [Button; 2] = [Button::One, Button::Two];
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Default for ButtonTiming {
+    fn default() -> ButtonTiming {
+        ButtonTiming {
+            debounce_ms: 20,
+            long_press_ms: 1000,
+            double_click_ms: 300,
+            chord_ms: 100,
+        }
+    }
+}
+
+impl Buttons {
+    /// Take the two switch pins. Switches already down when this is called
+    /// are reported as pressed once they've been debounced.
+    pub fn new(one: PF4<Input<PullUp>>, two: PF0<Input<PullUp>>, timing: ButtonTiming) -> Buttons {
+        Buttons {
+            one,
+            two,
+            timing,
+            states: [SwitchState::new(); 2],
+            chorded: false,
+            events: [None; QUEUE_LEN],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Change the timing.
+    pub fn set_timing(&mut self, timing: ButtonTiming) {
+        self.timing = timing;
+    }
+
+    /// Sample the switches and update the events. `now_ms` is the time in
+    /// milliseconds, and may wrap.
+    pub fn poll(&mut self, now_ms: u32) {
+        // The switches pull the pin to ground.
+        let raw = [
+            self.one.is_low().unwrap_or(false),
+            self.two.is_low().unwrap_or(false),
+        ];
+        for (index, &button) in BUTTONS.iter().enumerate() {
+            self.update(index, button, raw[index], now_ms);
+        }
+        let [one, two] = self.states;
+        if one.pressed && two.pressed {
+            let gap = one.pressed_at.wrapping_sub(two.pressed_at) as i32;
+            if !self.chorded && gap.unsigned_abs() <= self.timing.chord_ms {
+                self.chorded = true;
+                self.push(ButtonEvent::Chord);
+            }
+        } else if !one.pressed && !two.pressed {
+            self.chorded = false;
+        }
+    }
+
+    /// Take the oldest event not yet collected.
+    pub fn next_event(&mut self) -> Option<ButtonEvent> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.events[self.head].take();
+        self.head = (self.head + 1) % QUEUE_LEN;
+        self.len -= 1;
+        event
+    }
+
+    /// Is the switch down (after debouncing)?
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.states[index(button)].pressed
+    }
+
+    /// Is a debounce or long-press timer running? If not, nothing can
+    /// happen until a switch changes, so it's safe to stop polling until
+    /// the next edge.
+    pub fn needs_polling(&self) -> bool {
+        self.states
+            .iter()
+            .any(|s| s.raw != s.pressed || (s.pressed && !s.long_sent))
+    }
+
+    /// Give back the pins, as (SW1, SW2).
+    pub fn into_pins(self) -> (PF4<Input<PullUp>>, PF0<Input<PullUp>>) {
+        (self.one, self.two)
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl SwitchState {
+    const fn new() -> SwitchState {
+        SwitchState {
+            raw: false,
+            raw_since: 0,
+            pressed: false,
+            pressed_at: 0,
+            long_sent: false,
+            clicked_at: None,
+        }
+    }
+}
+
+impl Buttons {
+    fn update(&mut self, index: usize, button: Button, raw: bool, now: u32) {
+        let timing = self.timing;
+        let mut state = self.states[index];
+        if raw != state.raw {
+            state.raw = raw;
+            state.raw_since = now;
+        }
+        if state.raw != state.pressed && now.wrapping_sub(state.raw_since) >= timing.debounce_ms {
+            state.pressed = state.raw;
+            if state.pressed {
+                state.pressed_at = now;
+                state.long_sent = false;
+                self.push(ButtonEvent::Pressed(button));
+                if let Some(clicked_at) = state.clicked_at.take() {
+                    if now.wrapping_sub(clicked_at) <= timing.double_click_ms {
+                        self.push(ButtonEvent::DoubleClick(button));
+                    }
+                }
+            } else {
+                self.push(ButtonEvent::Released(button));
+                // A long press can't be the first half of a double click.
+                state.clicked_at = if state.long_sent { None } else { Some(now) };
+            }
+        }
+        if state.pressed
+            && !state.long_sent
+            && now.wrapping_sub(state.pressed_at) >= timing.long_press_ms
+        {
+            state.long_sent = true;
+            self.push(ButtonEvent::LongPress(button));
+        }
+        self.states[index] = state;
+    }
+
+    fn push(&mut self, event: ButtonEvent) {
+        if self.len < QUEUE_LEN {
+            self.events[(self.head + self.len) % QUEUE_LEN] = Some(event);
+            self.len += 1;
+        }
+    }
+}
+
+fn index(button: Button) -> usize {
+    match button {
+        Button::One => 0,
+        Button::Two => 1,
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
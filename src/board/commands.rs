@@ -0,0 +1,157 @@
+//! Ready-made commands for a `shell::Shell` on the console, which work
+//! whatever the shell's context is:
+//!
+//! * `uptime` - the time since boot
+//! * `heap` - how much of the heap is in use (with the `alloc` feature)
+//! * `gpio read pb1` - the level on a pin, from any port which is powered
+//! * `reset` - reset the chip
+//!
+//! `register` adds them all. Commands which need the hardware itself (the
+//! LED, an ADC) belong to the application - see `examples/shell.rs`.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::fmt::Write;
+
+use crate::shell::{Command, Error, Shell};
+use cortex_m::peripheral::SCB;
+use tm4c123x_hal::tm4c123x::{self, gpio_porta};
+
+use super::{console, time};
+#[cfg(feature = "alloc")]
+use crate::common::heap;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const PORT_NAMES: [&str; 6] = ["pa", "pb", "pc", "pd", "pe", "pf"];
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Add all of this module's commands to `shell`.
+pub fn register<C>(shell: &mut Shell<C>) -> Result<(), Error> {
+    shell.register(Command::new("uptime", "time since boot", uptime))?;
+    #[cfg(feature = "alloc")]
+    shell.register(Command::new("heap", "heap usage", heap))?;
+    shell.register(Command::new("gpio", "gpio read <pin> (e.g. pb1)", gpio).words(&["read"]))?;
+    shell.register(Command::new("reset", "reset the chip", reset))
+}
+
+/// `uptime`
+pub fn uptime<C>(_: &mut C, _: &[&str], out: &mut dyn Write) -> Result<(), &'static str> {
+    if !time::is_running() {
+        return Err("the clock isn't running (see time::init)");
+    }
+    let up = time::now().since_boot();
+    let _ = writeln!(out, "up {}.{:06} s", up.as_secs(), up.subsec_micros());
+    Ok(())
+}
+
+/// `heap`
+#[cfg(feature = "alloc")]
+pub fn heap<C>(_: &mut C, _: &[&str], out: &mut dyn Write) -> Result<(), &'static str> {
+    let stats = heap::stats();
+    let _ = writeln!(
+        out,
+        "{} of {} bytes used, {} free, {} at most, {} failed allocations",
+        stats.used, stats.size, stats.free, stats.high_water, stats.failed_allocations
+    );
+    Ok(())
+}
+
+/// `gpio read <pin>`, where the pin is `pa0` to `pf7`. A pin which isn't a
+/// digital input or output reads as 0.
+pub fn gpio<C>(_: &mut C, args: &[&str], out: &mut dyn Write) -> Result<(), &'static str> {
+    let pin = match args {
+        ["read", pin] => *pin,
+        _ => return Err("usage: gpio read <pin>"),
+    };
+    let (port, bit) = parse_pin(pin).ok_or("pins are pa0 to pf7")?;
+    let sysctl = unsafe { &*tm4c123x::SYSCTL::ptr() };
+    // Touching an unpowered port is a bus fault.
+    if sysctl.prgpio.read().bits() & (1 << port) == 0 {
+        return Err("that port isn't powered");
+    }
+    let level = (port_registers(port).data.read().bits() >> bit) & 1;
+    let _ = writeln!(out, "{} = {}", pin, level);
+    Ok(())
+}
+
+/// `reset`
+pub fn reset<C>(_: &mut C, _: &[&str], out: &mut dyn Write) -> Result<(), &'static str> {
+    let _ = writeln!(out, "resetting");
+    console::flush();
+    SCB::sys_reset();
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// `pb1` is port 1, bit 1.
+fn parse_pin(pin: &str) -> Option<(usize, u32)> {
+    if pin.len() != 3 {
+        return None;
+    }
+    let port = PORT_NAMES
+        .iter()
+        .position(|&name| pin[..2].eq_ignore_ascii_case(name))?;
+    let bit = pin[2..].parse::<u32>().ok().filter(|&bit| bit < 8)?;
+    Some((port, bit))
+}
+
+fn port_registers(port: usize) -> &'static gpio_porta::RegisterBlock {
+    unsafe {
+        match port {
+            0 => &*tm4c123x::GPIO_PORTA::ptr(),
+            1 => &*tm4c123x::GPIO_PORTB::ptr(),
+            2 => &*tm4c123x::GPIO_PORTC::ptr(),
+            3 => &*tm4c123x::GPIO_PORTD::ptr(),
+            4 => &*tm4c123x::GPIO_PORTE::ptr(),
+            _ => &*tm4c123x::GPIO_PORTF::ptr(),
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
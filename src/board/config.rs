@@ -0,0 +1,259 @@
+//! Start-up configuration for the Launchpad.
+//!
+//! The application picks its clock tree by naming a function returning a
+//! [`BoardConfig`] in `#[stellaris_launchpad::main(config = ...)]`. If it
+//! doesn't, the board starts with `BoardConfig::default()`, which runs the
+//! PLL at 80 MHz from the 16 MHz crystal.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use tm4c123x_hal::sysctl::{
+    CrystalFrequency, Divider, Oscillator, PllOutputFrequency, SystemClock,
+};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Where the system clock is derived from.
+pub enum ClockSource {
+    /// The main oscillator, driven by an external crystal. The Launchpad
+    /// is fitted with a 16 MHz crystal.
+    Main(CrystalFrequency),
+    /// The 16 MHz precision internal oscillator (PIOSC).
+    PrecisionInternal,
+    /// The precision internal oscillator divided by four (4 MHz). This
+    /// cannot feed the PLL, so the system clock runs from it directly (see
+    /// `BoardConfig::divider`).
+    PrecisionInternalDiv4,
+    /// The ~30 kHz low-frequency internal oscillator (LFIOSC). This cannot
+    /// feed the PLL, so the system clock runs from it directly (see
+    /// `BoardConfig::divider`).
+    LowFrequencyInternal,
+}
+
+/// Describes how `Board::new` should set up the chip.
+///
+/// The builder methods are `const`, so a configuration built in a `const`
+/// is checked at compile time - asking for the PLL with a source which can't
+/// drive it fails the build rather than panicking at start-up.
+///
+/// ```ignore
+/// #[stellaris_launchpad::main(config = config)]
+/// fn main(board: Board) {
+///     ...
+/// }
+///
+/// fn config() -> BoardConfig {
+///     BoardConfig::new().pll(PllOutputFrequency::_50_00mhz)
+/// }
+/// ```
+pub struct BoardConfig {
+    source: ClockSource,
+    system_clock: SystemClock,
+    mirror_crash: bool,
+    trap_divide_by_zero: bool,
+    trap_unaligned: bool,
+    stack_guard: bool,
+    pll_requested: bool,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl BoardConfig {
+    /// The default configuration - the 16 MHz crystal feeding the PLL, with
+    /// the system clock at 80 MHz.
+    pub const fn new() -> BoardConfig {
+        BoardConfig {
+            source: ClockSource::Main(CrystalFrequency::_16mhz),
+            system_clock: SystemClock::UsePll(PllOutputFrequency::_80_00mhz),
+            mirror_crash: false,
+            trap_divide_by_zero: false,
+            trap_unaligned: false,
+            stack_guard: false,
+            pll_requested: false,
+        }
+    }
+
+    /// Select the clock source. A source which can't feed the PLL runs the
+    /// system clock undivided, unless `divider` says otherwise.
+    ///
+    /// Panics if `pll` has been called and the source can't feed the PLL.
+    pub const fn source(mut self, source: ClockSource) -> BoardConfig {
+        if !source.feeds_pll() {
+            if self.pll_requested {
+                panic!(
+                    "BoardConfig: only the main and precision internal oscillators can feed the PLL"
+                );
+            }
+            if let SystemClock::UsePll(_) = self.system_clock {
+                self.system_clock = SystemClock::UseOscillator(Divider::_1);
+            }
+        }
+        self.source = source;
+        self
+    }
+
+    /// Use the main oscillator, with a crystal of the given frequency.
+    pub const fn crystal(self, crystal: CrystalFrequency) -> BoardConfig {
+        self.source(ClockSource::Main(crystal))
+    }
+
+    /// Run the system clock from the PLL, at the given frequency.
+    ///
+    /// Panics if the source is the precision internal oscillator divided by
+    /// four or the low-frequency internal oscillator, which can't feed the
+    /// PLL.
+    pub const fn pll(mut self, frequency: PllOutputFrequency) -> BoardConfig {
+        if !self.source.feeds_pll() {
+            panic!(
+                "BoardConfig: only the main and precision internal oscillators can feed the PLL"
+            );
+        }
+        self.system_clock = SystemClock::UsePll(frequency);
+        self.pll_requested = true;
+        self
+    }
+
+    /// Bypass the PLL and run the system clock directly from the clock
+    /// source, divided down by the given amount.
+    pub const fn divider(mut self, divider: Divider) -> BoardConfig {
+        self.system_clock = SystemClock::UseOscillator(divider);
+        self.pll_requested = false;
+        self
+    }
+
+    /// Run from the 16 MHz precision internal oscillator, with the PLL and
+    /// main oscillator left off. Useful for low-power firmware, or boards
+    /// without a crystal fitted.
+    pub const fn run_from_internal_oscillator(self) -> BoardConfig {
+        self.source(ClockSource::PrecisionInternal)
+            .divider(Divider::_1)
+    }
+
+    /// Copy any crash record found at start-up into the EEPROM (in the last
+    /// two 64-byte blocks), so it survives a power cycle. Off by default.
+    pub const fn mirror_crash_to_eeprom(mut self, enabled: bool) -> BoardConfig {
+        self.mirror_crash = enabled;
+        self
+    }
+
+    /// Make an integer divide by zero raise a UsageFault, rather than
+    /// quietly giving zero. Off by default.
+    pub const fn trap_divide_by_zero(mut self, enabled: bool) -> BoardConfig {
+        self.trap_divide_by_zero = enabled;
+        self
+    }
+
+    /// Make an unaligned word or halfword access raise a UsageFault, rather
+    /// than being split into smaller accesses by the core. Off by default.
+    pub const fn trap_unaligned(mut self, enabled: bool) -> BoardConfig {
+        self.trap_unaligned = enabled;
+        self
+    }
+
+    /// Use the MPU to make the bottom of the stack read-only, so a stack
+    /// overflow raises a MemManage fault instead of overwriting the heap.
+    /// Off by default. See `common::stack`.
+    pub const fn stack_guard(mut self, enabled: bool) -> BoardConfig {
+        self.stack_guard = enabled;
+        self
+    }
+
+    /// Should we copy crash records to the EEPROM?
+    pub(crate) fn mirrors_crash(&self) -> bool {
+        self.mirror_crash
+    }
+
+    /// Should we set up the MPU stack guard?
+    pub(crate) fn guards_stack(&self) -> bool {
+        self.stack_guard
+    }
+
+    /// Which optional UsageFault traps to enable, as (divide by zero,
+    /// unaligned).
+    pub(crate) fn traps(&self) -> (bool, bool) {
+        (self.trap_divide_by_zero, self.trap_unaligned)
+    }
+
+    /// Convert to the form the HAL wants.
+    pub(crate) fn oscillator(self) -> Oscillator {
+        match self.source {
+            ClockSource::Main(crystal) => Oscillator::Main(crystal, self.system_clock),
+            ClockSource::PrecisionInternal => Oscillator::PrecisionInternal(self.system_clock),
+            ClockSource::PrecisionInternalDiv4 => {
+                Oscillator::PrecisionInternalDiv4(divider_without_pll(self.system_clock))
+            }
+            ClockSource::LowFrequencyInternal => {
+                Oscillator::LowFrequencyInternal(divider_without_pll(self.system_clock))
+            }
+        }
+    }
+}
+
+impl Default for BoardConfig {
+    fn default() -> BoardConfig {
+        BoardConfig::new()
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl ClockSource {
+    /// Can this source drive the PLL?
+    const fn feeds_pll(&self) -> bool {
+        matches!(*self, ClockSource::Main(_) | ClockSource::PrecisionInternal)
+    }
+}
+
+/// The builder never lets a source which can't feed the PLL keep it.
+fn divider_without_pll(system_clock: SystemClock) -> Divider {
+    match system_clock {
+        SystemClock::UseOscillator(divider) => divider,
+        SystemClock::UsePll(_) => unreachable!(),
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
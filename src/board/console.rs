@@ -0,0 +1,520 @@
+//! The console: UART0, on PA0/PA1 through the debug USB port, at 115200 bps
+//! 8N1. Once started, anything can write to it with `println!`, `print!` and
+//! the logging macros (`error!`, `warn!`, `info!`, `debug!`, `trace!` and
+//! `log!`), without passing a UART around.
+//!
+//! ```ignore
+//! let porta = board::console::init(board.UART0, board.GPIO_PORTA, &board.power_control);
+//! console::set_filter(Filter::new(Level::Info).module("app::radio", Some(Level::Trace)));
+//! info!("reset cause: {:?}", board.reset_cause());
+//! ```
+//!
+//! Log lines carry the time since boot (once `time::init` has started the
+//! clock), the level and the module (see `log::write_record` for the
+//! format), and only appear if the filter lets them through. `println!` output is never filtered.
+//!
+//! Each message goes out whole, with the CPU waiting on the UART, so keep
+//! messages from interrupt handlers short - or call `use_dma`, and messages
+//! are copied into a buffer which the µDMA controller sends, so the CPU only
+//! waits when the buffer is full. If an interrupt handler writes
+//! while the console is busy with the message it interrupted, its message is
+//! dropped (and counted - see `dropped`) rather than waiting for a console
+//! that can't become free. Messages written before `init` are dropped too.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::cell::{Cell, RefCell};
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::{ptr, slice};
+
+use cortex_m::interrupt::{self, Mutex};
+use tm4c123x_hal::gpio::gpioa::{GpioControl, PA2, PA3, PA4, PA5, PA6, PA7};
+use tm4c123x_hal::gpio::{GpioExt, Tristate};
+use tm4c123x_hal::sysctl::{self, Domain, PowerControl, PowerState, RunMode};
+use tm4c123x_hal::tm4c123x::{uart0, GPIO_PORTA, UART0};
+
+use super::dma::{self, Dma, Target, Transfer, TxChannel};
+use super::{clocks, time};
+use crate::log::{self, Filter, Level};
+use crate::shell::Shell;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Somewhere to write which goes to the console, for code which wants a
+/// `fmt::Write` (a `shell::Shell`, say). Each write is a message of its own.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Output;
+
+/// Port A without PA0 and PA1, which belong to the console. See `init`.
+#[allow(missing_docs)]
+pub struct PortA {
+    pub control: GpioControl,
+    pub pa2: PA2<Tristate>,
+    pub pa3: PA3<Tristate>,
+    pub pa4: PA4<Tristate>,
+    pub pa5: PA5<Tristate>,
+    pub pa6: PA6<Tristate>,
+    pub pa7: PA7<Tristate>,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The console's bit rate.
+pub const BAUD_RATE: u32 = 115_200;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// Writes to the UART (or the DMA buffer), turning `\n` into `\r\n`. Only
+/// made while holding the console.
+struct Writer {
+    dma: bool,
+}
+
+/// The part of `TX_BUFFER` waiting to be sent, or being sent, by DMA.
+#[derive(Clone, Copy)]
+struct Queue {
+    start: usize,
+    len: usize,
+}
+
+/// The console's DMA channel, with or without a transfer going.
+enum Sender {
+    Idle(TxChannel<'static>),
+    Sending(Transfer<TxChannel<'static>, &'static [u8]>),
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Held by whoever is writing a message.
+static BUSY: AtomicBool = AtomicBool::new(false);
+
+static DROPPED: AtomicU32 = AtomicU32::new(0);
+
+static FILTER: Mutex<Cell<Filter>> = Mutex::new(Cell::new(Filter::new(Level::Info)));
+
+/// Set by `use_dma`.
+static SENDER: Mutex<RefCell<Option<Sender>>> = Mutex::new(RefCell::new(None));
+
+static QUEUE: Mutex<Cell<Queue>> = Mutex::new(Cell::new(Queue { start: 0, len: 0 }));
+
+/// Only written through `QUEUE`'s free end, and only read by the DMA
+/// controller.
+static mut TX_BUFFER: [u8; TX_BUFFER_SIZE] = [0; TX_BUFFER_SIZE];
+
+const TX_BUFFER_SIZE: usize = 512;
+
+// UARTFR
+const FR_BUSY: u32 = 1 << 3;
+const FR_TXFF: u32 = 1 << 5;
+const FR_RXFE: u32 = 1 << 4;
+
+// UARTLCRH: 8 data bits, FIFOs on
+const LCRH_WLEN_8: u32 = 0x3 << 5;
+const LCRH_FEN: u32 = 1 << 4;
+
+// UARTCTL
+const CTL_UARTEN: u32 = 1 << 0;
+const CTL_TXE: u32 = 1 << 8;
+const CTL_RXE: u32 = 1 << 9;
+
+// PA0 (U0Rx) and PA1 (U0Tx), and their GPIOPCTL mux value
+const CONSOLE_PINS: u32 = 0b11;
+const PCTL_UART0: u32 = 0x11;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Start the console. Call once, after `Board::new`. UART0 and PA0/PA1
+/// belong to the console from then on, and the rest of port A is handed
+/// back.
+pub fn init(_uart: UART0, porta: GPIO_PORTA, power_control: &PowerControl) -> PortA {
+    // Splitting resets the port, so it must come before the pins are set up.
+    let pins = porta.split(power_control);
+    configure(power_control, clocks().sysclk.0);
+    STARTED.store(true, Ordering::Release);
+    PortA {
+        control: pins.control,
+        pa2: pins.pa2,
+        pa3: pins.pa3,
+        pa4: pins.pa4,
+        pa5: pins.pa5,
+        pa6: pins.pa6,
+        pa7: pins.pa7,
+    }
+}
+
+/// Send from now on with the µDMA controller, through a 512 byte buffer,
+/// rather than having the CPU wait on the UART. UART0's interrupt must call
+/// `dma::on_interrupt` (the `dma` feature does that).
+///
+/// Panics if the console hasn't been started.
+pub fn use_dma(dma: &Dma) -> Result<(), dma::Error> {
+    assert!(is_started(), "start the console before giving it DMA");
+    let mut channel = dma.tx_channel_for(Target::Uart0)?;
+    channel.set_callback(Some(send_queued));
+    flush();
+    interrupt::free(|cs| *SENDER.borrow(cs).borrow_mut() = Some(Sender::Idle(channel)));
+    Ok(())
+}
+
+/// Has the console been started?
+pub fn is_started() -> bool {
+    STARTED.load(Ordering::Acquire)
+}
+
+/// Choose which log messages are written.
+pub fn set_filter(filter: Filter) {
+    interrupt::free(|cs| FILTER.borrow(cs).set(filter));
+}
+
+/// Which log messages are written.
+pub fn filter() -> Filter {
+    interrupt::free(|cs| FILTER.borrow(cs).get())
+}
+
+/// Would a message at `level` from `module_path` be written? Lets you skip
+/// working out something that's only there to be logged.
+pub fn enabled(level: Level, module_path: &str) -> bool {
+    is_started() && filter().enabled(level, module_path)
+}
+
+/// How many messages have been dropped because the console was busy.
+pub fn dropped() -> u32 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// Take a byte which has arrived on the console, if there is one.
+pub fn read() -> Option<u8> {
+    if !is_started() {
+        return None;
+    }
+    let uart = uart();
+    if uart.fr.read().bits() & FR_RXFE != 0 {
+        None
+    } else {
+        // Errors are in bits 8 to 11; a bad byte is still a byte.
+        Some(uart.dr.read().bits() as u8)
+    }
+}
+
+/// Wait until everything written has gone out of the UART.
+pub fn flush() {
+    if is_started() {
+        while interrupt::free(|cs| QUEUE.borrow(cs).get().len) != 0 {
+            send_queued();
+        }
+        while uart().fr.read().bits() & FR_BUSY != 0 {}
+    }
+}
+
+/// Feed `shell` whatever has arrived on the console, and let it reply.
+/// Call this from the main loop.
+pub fn poll<C>(shell: &mut Shell<C>, context: &mut C) {
+    while let Some(byte) = read() {
+        // `Output` never fails.
+        let _ = shell.feed(byte, context, &mut Output);
+    }
+}
+
+/// Set UART0 up on PA0/PA1 for the console, whatever state they were left
+/// in. The panic and fault handlers use this too.
+pub(crate) fn configure(power_control: &PowerControl, sysclk_hz: u32) {
+    sysctl::control_power(power_control, Domain::Uart0, RunMode::Run, PowerState::On);
+    sysctl::reset(power_control, Domain::Uart0);
+    sysctl::control_power(power_control, Domain::GpioA, RunMode::Run, PowerState::On);
+
+    let port = unsafe { &*GPIO_PORTA::ptr() };
+    port.afsel
+        .modify(|r, w| unsafe { w.bits(r.bits() | CONSOLE_PINS) });
+    port.pctl
+        .modify(|r, w| unsafe { w.bits((r.bits() & !0xFF) | PCTL_UART0) });
+    port.den
+        .modify(|r, w| unsafe { w.bits(r.bits() | CONSOLE_PINS) });
+
+    // The divisor is sysclk / (16 * baud), in 1/64ths.
+    let divisor = (sysclk_hz * 4 + BAUD_RATE / 2) / BAUD_RATE;
+    let uart = uart();
+    uart.ctl.write(|w| unsafe { w.bits(0) });
+    uart.ibrd.write(|w| unsafe { w.bits(divisor >> 6) });
+    uart.fbrd.write(|w| unsafe { w.bits(divisor & 0x3F) });
+    uart.lcrh
+        .write(|w| unsafe { w.bits(LCRH_WLEN_8 | LCRH_FEN) });
+    uart.ctl
+        .write(|w| unsafe { w.bits(CTL_UARTEN | CTL_TXE | CTL_RXE) });
+}
+
+/// Put a byte in the transmit FIFO, unless it's full. Doesn't wait, and
+/// doesn't care who else is writing.
+pub(crate) fn try_write_byte(byte: u8) -> bool {
+    let uart = uart();
+    if uart.fr.read().bits() & FR_TXFF != 0 {
+        return false;
+    }
+    uart.dr.write(|w| unsafe { w.bits(u32::from(byte)) });
+    true
+}
+
+impl fmt::Write for Output {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        with_console(|w| w.write_str(s));
+        Ok(())
+    }
+}
+
+/// Used by `print!` and `println!`.
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    with_console(|w| w.write_fmt(args));
+}
+
+/// Used by `log!` and friends.
+#[doc(hidden)]
+pub fn _log(level: Level, module_path: &str, args: fmt::Arguments) {
+    if !enabled(level, module_path) {
+        return;
+    }
+    let timestamp = if time::is_running() {
+        Some(time::now().since_boot())
+    } else {
+        None
+    };
+    with_console(|w| log::write_record(w, timestamp, level, module_path, args));
+}
+
+/// Write to the console, like `std`'s `print!`.
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::board::console::_print(format_args!($($arg)*))
+    };
+}
+
+/// Write a line to the console, like `std`'s `println!`.
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::board::console::_print(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        $crate::board::console::_print(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+/// Log a message at the given `log::Level`, if the console's filter lets it
+/// through.
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {
+        $crate::board::console::_log($level, module_path!(), format_args!($($arg)+))
+    };
+}
+
+/// Log a message at `Level::Error`.
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        $crate::board::console::_log(
+            $crate::log::Level::Error,
+            module_path!(),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Log a message at `Level::Warn`.
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        $crate::board::console::_log(
+            $crate::log::Level::Warn,
+            module_path!(),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Log a message at `Level::Info`.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        $crate::board::console::_log(
+            $crate::log::Level::Info,
+            module_path!(),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Log a message at `Level::Debug`.
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        $crate::board::console::_log(
+            $crate::log::Level::Debug,
+            module_path!(),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Log a message at `Level::Trace`.
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        $crate::board::console::_log(
+            $crate::log::Level::Trace,
+            module_path!(),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Hold the console while `f` writes a message, or drop the message if the
+/// console isn't free.
+fn with_console<F>(f: F)
+where
+    F: FnOnce(&mut Writer) -> fmt::Result,
+{
+    if !is_started() {
+        return;
+    }
+    if BUSY
+        .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    let mut writer = Writer {
+        dma: interrupt::free(|cs| SENDER.borrow(cs).borrow().is_some()),
+    };
+    // Nothing to be done about a failure here - we are the error channel.
+    let _ = f(&mut writer);
+    if writer.dma {
+        send_queued();
+    }
+    BUSY.store(false, Ordering::Release);
+}
+
+impl Writer {
+    fn write_byte(&mut self, byte: u8) {
+        if self.dma {
+            while !queue_byte(byte) {
+                send_queued();
+            }
+        } else {
+            while !try_write_byte(byte) {}
+        }
+    }
+}
+
+impl fmt::Write for Writer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            if byte == b'\n' {
+                self.write_byte(b'\r');
+            }
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
+
+/// Add a byte to the end of the DMA queue, unless it's full.
+fn queue_byte(byte: u8) -> bool {
+    interrupt::free(|cs| {
+        let queue = QUEUE.borrow(cs);
+        let mut waiting = queue.get();
+        if waiting.len == TX_BUFFER_SIZE {
+            return false;
+        }
+        let end = (waiting.start + waiting.len) % TX_BUFFER_SIZE;
+        unsafe {
+            (ptr::addr_of_mut!(TX_BUFFER) as *mut u8)
+                .add(end)
+                .write(byte)
+        };
+        waiting.len += 1;
+        queue.set(waiting);
+        true
+    })
+}
+
+/// If the last transfer has finished, take what it sent off the queue and
+/// start sending the rest. Called from the DMA interrupt, and by anything
+/// waiting for the queue to drain.
+fn send_queued() {
+    interrupt::free(|cs| {
+        let mut sender = SENDER.borrow(cs).borrow_mut();
+        let queue = QUEUE.borrow(cs);
+        let mut waiting = queue.get();
+        let channel = match sender.take() {
+            None => return,
+            Some(Sender::Idle(channel)) => channel,
+            Some(Sender::Sending(transfer)) => {
+                if !transfer.is_done() {
+                    *sender = Some(Sender::Sending(transfer));
+                    return;
+                }
+                let (channel, sent) = transfer.wait();
+                waiting.start = (waiting.start + sent.len()) % TX_BUFFER_SIZE;
+                waiting.len -= sent.len();
+                channel
+            }
+        };
+        // Up to the end of the buffer; the rest goes next time.
+        let len = waiting.len.min(TX_BUFFER_SIZE - waiting.start);
+        *sender = Some(if len == 0 {
+            Sender::Idle(channel)
+        } else {
+            let data = unsafe {
+                let buffer = ptr::addr_of!(TX_BUFFER) as *const u8;
+                slice::from_raw_parts(buffer.add(waiting.start), len)
+            };
+            Sender::Sending(channel.write(data))
+        });
+        queue.set(waiting);
+    });
+}
+
+fn uart() -> &'static uart0::RegisterBlock {
+    unsafe { &*UART0::ptr() }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,427 @@
+//! A record of the last panic or fault, kept across a reset.
+//!
+//! The panic and fault handlers fill in a checksummed record in a RAM section
+//! that start-up neither initialises nor zeroes (`.crash_record`, see
+//! `memory.x.in`). On the next boot, `Board::new` checks it, adds the reset
+//! cause, and makes it available as `Board::last_crash()`. A power cycle
+//! loses it, unless it was mirrored to the EEPROM with
+//! `BoardConfig::mirror_crash_to_eeprom`.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::fmt;
+use core::mem::size_of;
+use core::ptr;
+
+use cortex_m::peripheral::SCB;
+use cortex_m_rt::ExceptionFrame;
+use tm4c123x_hal::tm4c123x::EEPROM;
+
+use super::eeprom::{self, EepromError, EEPROM_WORDS};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// The kind of failure that left a crash record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashKind {
+    /// Something called `panic!`.
+    Panic,
+    /// An allocation failed.
+    OutOfMemory,
+    /// The entry point returned an `Err`.
+    MainReturnedError,
+    /// A HardFault exception.
+    HardFault,
+    /// A MemManage fault exception.
+    MemoryManagement,
+    /// A BusFault exception.
+    BusFault,
+    /// A UsageFault exception.
+    UsageFault,
+    /// An interrupt fired which had no handler.
+    UnexpectedInterrupt,
+    /// The record's kind wasn't one we know about.
+    Unknown,
+}
+
+/// Why the chip last came out of reset, from the SYSCTL RESC register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResetCause(u32);
+
+/// What we know about the last panic or fault.
+#[derive(Clone, Copy)]
+pub struct CrashRecord {
+    raw: RawRecord,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// The record as it sits in RAM (and EEPROM). Exactly 32 words.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct RawRecord {
+    magic: u32,
+    kind: u32,
+    detail: u32,
+    pc: u32,
+    lr: u32,
+    xpsr: u32,
+    cfsr: u32,
+    hfsr: u32,
+    mmfar: u32,
+    bfar: u32,
+    reset_cause: u32,
+    message_len: u32,
+    message: [u8; MESSAGE_LEN],
+    checksum: u32,
+}
+
+/// Collects formatted text into the record, dropping whatever doesn't fit.
+struct MessageWriter<'a> {
+    record: &'a mut RawRecord,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const MAGIC: u32 = 0xC7A5_4ED0;
+
+const MESSAGE_LEN: usize = 76;
+
+const RECORD_WORDS: usize = 32;
+
+/// Where the mirrored copy lives - the last two blocks of the EEPROM.
+const EEPROM_ADDRESS: usize = EEPROM_WORDS - RECORD_WORDS;
+
+const RESC_EXT: u32 = 1 << 0;
+const RESC_POR: u32 = 1 << 1;
+const RESC_BOR: u32 = 1 << 2;
+const RESC_WDT0: u32 = 1 << 3;
+const RESC_SW: u32 = 1 << 4;
+const RESC_WDT1: u32 = 1 << 5;
+const RESC_MOSCFAIL: u32 = 1 << 16;
+
+#[link_section = ".crash_record"]
+static mut RECORD: RawRecord = RawRecord::EMPTY;
+
+// Make sure the record is the size the EEPROM layout assumes.
+const _RECORD_SIZE_CHECK: [(); RECORD_WORDS * 4] = [(); size_of::<RawRecord>()];
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl CrashRecord {
+    /// What kind of failure this was.
+    pub fn kind(&self) -> CrashKind {
+        CrashKind::from_u32(self.raw.kind)
+    }
+
+    /// Kind-specific detail - for `UnexpectedInterrupt`, the IRQ number.
+    pub fn detail(&self) -> u32 {
+        self.raw.detail
+    }
+
+    /// The panic message, or other description, truncated to fit.
+    pub fn message(&self) -> &str {
+        let len = (self.raw.message_len as usize).min(MESSAGE_LEN);
+        let bytes = &self.raw.message[..len];
+        match core::str::from_utf8(bytes) {
+            Ok(s) => s,
+            // Truncation may have split a character
+            Err(e) => core::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or(""),
+        }
+    }
+
+    /// The stacked program counter, or zero if there was no exception frame.
+    pub fn pc(&self) -> u32 {
+        self.raw.pc
+    }
+
+    /// The stacked link register, or zero if there was no exception frame.
+    pub fn lr(&self) -> u32 {
+        self.raw.lr
+    }
+
+    /// The stacked xPSR, or zero if there was no exception frame.
+    pub fn xpsr(&self) -> u32 {
+        self.raw.xpsr
+    }
+
+    /// The Configurable Fault Status Register at the time of the crash.
+    pub fn cfsr(&self) -> u32 {
+        self.raw.cfsr
+    }
+
+    /// The HardFault Status Register at the time of the crash.
+    pub fn hfsr(&self) -> u32 {
+        self.raw.hfsr
+    }
+
+    /// The MemManage Fault Address Register at the time of the crash.
+    pub fn mmfar(&self) -> u32 {
+        self.raw.mmfar
+    }
+
+    /// The BusFault Address Register at the time of the crash.
+    pub fn bfar(&self) -> u32 {
+        self.raw.bfar
+    }
+
+    /// How the chip was reset after the crash.
+    pub fn reset_cause(&self) -> ResetCause {
+        ResetCause(self.raw.reset_cause)
+    }
+
+    /// Copy the record into the EEPROM, which must have been initialised.
+    pub(crate) fn store(&self, eeprom: &EEPROM) -> Result<(), EepromError> {
+        eeprom::write(eeprom, EEPROM_ADDRESS, &self.raw.to_words())
+    }
+
+    /// Fetch the copy from the EEPROM, if there's a valid one.
+    pub(crate) fn load(eeprom: &EEPROM) -> Result<Option<CrashRecord>, EepromError> {
+        let mut words = [0u32; RECORD_WORDS];
+        eeprom::read(eeprom, EEPROM_ADDRESS, &mut words)?;
+        let raw = RawRecord::from_words(&words);
+        Ok(if raw.is_valid() {
+            Some(CrashRecord { raw })
+        } else {
+            None
+        })
+    }
+
+    /// Invalidate the copy in the EEPROM.
+    pub(crate) fn erase(eeprom: &EEPROM) -> Result<(), EepromError> {
+        eeprom::write(eeprom, EEPROM_ADDRESS, &[0])
+    }
+}
+
+impl fmt::Debug for CrashRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CrashRecord")
+            .field("kind", &self.kind())
+            .field("detail", &self.detail())
+            .field("message", &self.message())
+            .field("pc", &format_args!("{:#010x}", self.pc()))
+            .field("lr", &format_args!("{:#010x}", self.lr()))
+            .field("xpsr", &format_args!("{:#010x}", self.xpsr()))
+            .field("cfsr", &format_args!("{:#010x}", self.cfsr()))
+            .field("hfsr", &format_args!("{:#010x}", self.hfsr()))
+            .field("mmfar", &format_args!("{:#010x}", self.mmfar()))
+            .field("bfar", &format_args!("{:#010x}", self.bfar()))
+            .field("reset_cause", &self.reset_cause())
+            .finish()
+    }
+}
+
+impl ResetCause {
+    /// The raw RESC bits.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// The RST pin was asserted (e.g. the reset button).
+    pub fn external(&self) -> bool {
+        self.0 & RESC_EXT != 0
+    }
+
+    /// Power was applied.
+    pub fn power_on(&self) -> bool {
+        self.0 & RESC_POR != 0
+    }
+
+    /// The supply dipped below the brown-out threshold.
+    pub fn brown_out(&self) -> bool {
+        self.0 & RESC_BOR != 0
+    }
+
+    /// One of the watchdogs expired.
+    pub fn watchdog(&self) -> bool {
+        self.0 & (RESC_WDT0 | RESC_WDT1) != 0
+    }
+
+    /// Software asked for a reset (e.g. `SCB::sys_reset()`).
+    pub fn software(&self) -> bool {
+        self.0 & RESC_SW != 0
+    }
+
+    /// The main oscillator failed.
+    pub fn main_oscillator_failure(&self) -> bool {
+        self.0 & RESC_MOSCFAIL != 0
+    }
+}
+
+/// Record a failure. Only the first call after a boot has any effect, so the
+/// original fault isn't overwritten by whatever it causes next. Must be
+/// called with interrupts disabled, or from a fault handler.
+pub(crate) fn record(
+    kind: CrashKind,
+    detail: u32,
+    frame: Option<&ExceptionFrame>,
+    message: fmt::Arguments,
+) {
+    let record = unsafe { &mut *ptr::addr_of_mut!(RECORD) };
+    if record.is_valid() {
+        return;
+    }
+    let scb = unsafe { &*SCB::ptr() };
+    *record = RawRecord::EMPTY;
+    record.kind = kind as u32;
+    record.detail = detail;
+    if let Some(frame) = frame {
+        record.pc = frame.pc;
+        record.lr = frame.lr;
+        record.xpsr = frame.xpsr;
+    }
+    record.cfsr = scb.cfsr.read();
+    record.hfsr = scb.hfsr.read();
+    record.mmfar = scb.mmfar.read();
+    record.bfar = scb.bfar.read();
+    let _ = fmt::write(&mut MessageWriter { record }, message);
+    record.magic = MAGIC;
+    record.checksum = record.calculate_checksum();
+}
+
+/// The kind and detail of the failure recorded this boot, if there has been
+/// one.
+pub(crate) fn current() -> Option<(CrashKind, u32)> {
+    let record = unsafe { &*ptr::addr_of!(RECORD) };
+    if record.is_valid() {
+        Some((CrashKind::from_u32(record.kind), record.detail))
+    } else {
+        None
+    }
+}
+
+/// Called once at start-up. Returns the record left by the previous boot (if
+/// any) with the reset cause filled in, and clears it ready for this boot.
+pub(crate) fn take_last_crash(reset_cause: ResetCause) -> Option<CrashRecord> {
+    let record = unsafe { &mut *ptr::addr_of_mut!(RECORD) };
+    let result = if record.is_valid() {
+        let mut raw = *record;
+        raw.reset_cause = reset_cause.0;
+        raw.checksum = raw.calculate_checksum();
+        Some(CrashRecord { raw })
+    } else {
+        None
+    };
+    *record = RawRecord::EMPTY;
+    result
+}
+
+/// Wrap the raw RESC bits.
+pub(crate) fn reset_cause(resc: u32) -> ResetCause {
+    ResetCause(resc)
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl CrashKind {
+    fn from_u32(value: u32) -> CrashKind {
+        match value {
+            x if x == CrashKind::Panic as u32 => CrashKind::Panic,
+            x if x == CrashKind::OutOfMemory as u32 => CrashKind::OutOfMemory,
+            x if x == CrashKind::MainReturnedError as u32 => CrashKind::MainReturnedError,
+            x if x == CrashKind::HardFault as u32 => CrashKind::HardFault,
+            x if x == CrashKind::MemoryManagement as u32 => CrashKind::MemoryManagement,
+            x if x == CrashKind::BusFault as u32 => CrashKind::BusFault,
+            x if x == CrashKind::UsageFault as u32 => CrashKind::UsageFault,
+            x if x == CrashKind::UnexpectedInterrupt as u32 => CrashKind::UnexpectedInterrupt,
+            _ => CrashKind::Unknown,
+        }
+    }
+}
+
+impl RawRecord {
+    const EMPTY: RawRecord = RawRecord {
+        magic: 0,
+        kind: 0,
+        detail: 0,
+        pc: 0,
+        lr: 0,
+        xpsr: 0,
+        cfsr: 0,
+        hfsr: 0,
+        mmfar: 0,
+        bfar: 0,
+        reset_cause: 0,
+        message_len: 0,
+        message: [0; MESSAGE_LEN],
+        checksum: 0,
+    };
+
+    fn to_words(&self) -> [u32; RECORD_WORDS] {
+        // Both are plain old data of the same size (checked above)
+        unsafe { core::mem::transmute(*self) }
+    }
+
+    fn from_words(words: &[u32; RECORD_WORDS]) -> RawRecord {
+        unsafe { core::mem::transmute(*words) }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.magic == MAGIC && self.checksum == self.calculate_checksum()
+    }
+
+    /// CRC-32 (IEEE) over everything but the checksum itself.
+    fn calculate_checksum(&self) -> u32 {
+        let words = self.to_words();
+        let mut crc = !0u32;
+        for word in &words[..RECORD_WORDS - 1] {
+            for byte in &word.to_le_bytes() {
+                crc ^= u32::from(*byte);
+                for _ in 0..8 {
+                    let mask = (crc & 1).wrapping_neg();
+                    crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+                }
+            }
+        }
+        !crc
+    }
+}
+
+impl<'a> fmt::Write for MessageWriter<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let len = self.record.message_len as usize;
+        let space = MESSAGE_LEN - len;
+        let take = s.len().min(space);
+        self.record.message[len..len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.record.message_len += take as u32;
+        Ok(())
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
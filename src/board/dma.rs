@@ -0,0 +1,729 @@
+//! The µDMA controller, for moving data between memory and the UARTs and
+//! SSI ports without the CPU.
+//!
+//! Start the controller with `Dma::new`, then ask it for a channel to or
+//! from a peripheral. The peripheral itself is set up as usual (with
+//! `cpu::serial::Serial` or `cpu::spi::Spi`); the channel just moves bytes
+//! in or out of its FIFO, and borrows the `Serial` or `Spi` so nothing else
+//! can use the peripheral meanwhile. A transfer owns its channel and its
+//! buffer until it's done, and since the buffers are `'static` nothing can
+//! go wrong if a transfer is forgotten. Dropping an unfinished transfer
+//! stops it.
+//!
+//! ```ignore
+//! let dma = Dma::new(board.UDMA, &board.power_control);
+//! let mut spi = Spi::spi2(board.SSI2, (sck, miso, mosi), MODE, Hertz(1_000_000), ...);
+//! let (tx, rx) = dma.channels(&mut spi)?;
+//! let reply = cortex_m::singleton!(: [u8; 4] = [0; 4]).unwrap();
+//! let reading = rx.read(reply);
+//! let writing = tx.write(&COMMAND);
+//! let (tx, _) = writing.wait();
+//! let (rx, reply) = reading.await;
+//! ```
+//!
+//! A transfer can be waited for (`Transfer::wait`), polled
+//! (`Transfer::is_done`) or awaited, and a channel can have a function which
+//! is called from the interrupt when each of its transfers is done.
+//!
+//! The console can send through DMA too - see `console::use_dma` - and
+//! `DmaSpi` gives drivers which use the blocking `embedded_hal` SPI traits
+//! (the MFRC522's, say) an `Spi` whose bytes are moved by DMA.
+//!
+//! The controller signals the end of a transfer on the peripheral's
+//! interrupt, so that interrupt must call `on_interrupt`. The `dma` feature
+//! binds UART0 to UART2 and SSI0 to SSI3 to do so (UART0 to UART2 also
+//! serve `board::uart`, with their `uartN` features). Without it, the
+//! application's handler for each peripheral it uses DMA with must call
+//! `on_interrupt`.
+//!
+//! See `examples/dma_console.rs`.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::ptr;
+use core::sync::atomic::{self, AtomicU32, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use cortex_m::interrupt::{self, Mutex};
+use cortex_m::peripheral::NVIC;
+use embedded_hal::blocking::spi;
+use tm4c123x_hal::serial::Serial;
+use tm4c123x_hal::spi::Spi;
+use tm4c123x_hal::sysctl::{self, Domain, PowerControl, PowerState, RunMode};
+use tm4c123x_hal::tm4c123x::{self, ssi0, udma, Interrupt, UDMA};
+
+use super::uart::Port;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// The µDMA controller. See the module documentation.
+pub struct Dma {
+    _udma: UDMA,
+}
+
+/// A peripheral which a channel can serve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// UART0 (channels 8 and 9)
+    Uart0,
+    /// UART1 (channels 22 and 23)
+    Uart1,
+    /// UART2 (channels 12 and 13, shared with SSI2)
+    Uart2,
+    /// SSI0 (channels 10 and 11)
+    Ssi0,
+    /// SSI1 (channels 24 and 25)
+    Ssi1,
+    /// SSI2 (channels 12 and 13, shared with UART2)
+    Ssi2,
+    /// SSI3 (channels 14 and 15)
+    Ssi3,
+}
+
+/// A driver for a peripheral which a channel can serve: a `Serial` on
+/// UART0 to UART2, or an `Spi` on SSI0 to SSI3.
+pub trait Peripheral {
+    /// Which peripheral it drives.
+    const TARGET: Target;
+}
+
+/// An `Spi` which moves its bytes with DMA. Each call still waits until
+/// its transfer is done, but the CPU isn't feeding the FIFO meanwhile.
+pub struct DmaSpi<SPI, PINS> {
+    spi: Spi<SPI, PINS>,
+    tx: Channel,
+    rx: Channel,
+}
+
+/// Why a channel can't be had.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Someone else has the channel.
+    InUse,
+}
+
+/// A channel which writes memory to a peripheral. It borrows the
+/// peripheral's driver for `'a`.
+#[derive(Debug)]
+pub struct TxChannel<'a> {
+    channel: Channel,
+    _peripheral: PhantomData<&'a mut ()>,
+}
+
+/// A channel which reads a peripheral into memory. It borrows the
+/// peripheral's driver for `'a`.
+#[derive(Debug)]
+pub struct RxChannel<'a> {
+    channel: Channel,
+    _peripheral: PhantomData<&'a mut ()>,
+}
+
+/// A transfer in progress. It completes (as a future, too) with the channel
+/// and the buffer.
+#[derive(Debug)]
+pub struct Transfer<C, B> {
+    number: usize,
+    parts: Option<(C, B)>,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The most bytes one transfer can move.
+pub const MAX_TRANSFER: usize = 1024;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Tx,
+    Rx,
+}
+
+/// A channel we've handed out. Giving it back (dropping it) frees it.
+#[derive(Debug)]
+struct Channel {
+    number: usize,
+    target: Target,
+    direction: Direction,
+}
+
+/// The controller's idea of a channel: where the data ends up, where it
+/// comes from and how much of it there is.
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Descriptor {
+    source_end: u32,
+    destination_end: u32,
+    control: u32,
+    _unused: u32,
+}
+
+/// The primary descriptors for all 32 channels. We only use basic mode, so
+/// there's no alternate half.
+#[repr(C, align(1024))]
+struct ControlTable([Descriptor; 32]);
+
+/// What the interrupt needs to know about a channel.
+struct Slot {
+    waker: Option<Waker>,
+    callback: Option<fn()>,
+    running: Option<(Target, Direction)>,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+static mut CONTROL_TABLE: ControlTable = ControlTable(
+    [Descriptor {
+        source_end: 0,
+        destination_end: 0,
+        control: 0,
+        _unused: 0,
+    }; 32],
+);
+
+/// One bit per channel handed out.
+static ALLOCATED: AtomicU32 = AtomicU32::new(0);
+
+static SLOTS: Mutex<RefCell<[Slot; 32]>> = Mutex::new(RefCell::new([EMPTY_SLOT; 32]));
+
+const EMPTY_SLOT: Slot = Slot {
+    waker: None,
+    callback: None,
+    running: None,
+};
+
+// DMACFG
+const CFG_MASTEN: u32 = 1 << 0;
+
+// DMACHCTL
+const CTL_DSTINC_NONE: u32 = 3 << 30;
+const CTL_SRCINC_NONE: u32 = 3 << 26;
+const CTL_XFERSIZE_SHIFT: u32 = 4;
+const CTL_XFERMODE_BASIC: u32 = 1;
+
+// SSISR
+const SR_RNE: u32 = 1 << 2;
+
+// UARTDMACTL and SSIDMACTL
+const DMACTL_RXDMAE: u32 = 1 << 0;
+const DMACTL_TXDMAE: u32 = 1 << 1;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Dma {
+    /// Power up the controller and point it at the control table.
+    pub fn new(udma: UDMA, power_control: &PowerControl) -> Dma {
+        sysctl::control_power(
+            power_control,
+            Domain::MicroDma,
+            RunMode::Run,
+            PowerState::On,
+        );
+        sysctl::reset(power_control, Domain::MicroDma);
+        let table = ptr::addr_of!(CONTROL_TABLE) as u32;
+        udma.cfg.write(|w| unsafe { w.bits(CFG_MASTEN) });
+        udma.ctlbase.write(|w| unsafe { w.bits(table) });
+        Dma { _udma: udma }
+    }
+
+    /// A channel for writing to `peripheral`.
+    pub fn tx_channel<'a, P: Peripheral>(
+        &self,
+        _peripheral: &'a mut P,
+    ) -> Result<TxChannel<'a>, Error> {
+        TxChannel::allocate(P::TARGET)
+    }
+
+    /// A channel for reading from `peripheral`.
+    pub fn rx_channel<'a, P: Peripheral>(
+        &self,
+        _peripheral: &'a mut P,
+    ) -> Result<RxChannel<'a>, Error> {
+        RxChannel::allocate(P::TARGET)
+    }
+
+    /// Channels both ways for `peripheral` - for an `Spi`, say, where every
+    /// byte written is a byte read.
+    pub fn channels<'a, P: Peripheral>(
+        &self,
+        _peripheral: &'a mut P,
+    ) -> Result<(TxChannel<'a>, RxChannel<'a>), Error> {
+        Ok((
+            TxChannel::allocate(P::TARGET)?,
+            RxChannel::allocate(P::TARGET)?,
+        ))
+    }
+
+    /// A channel for writing to `target`, for a driver in the crate which
+    /// owns the peripheral some other way (the console owns UART0).
+    pub(crate) fn tx_channel_for(&self, target: Target) -> Result<TxChannel<'static>, Error> {
+        TxChannel::allocate(target)
+    }
+}
+
+impl<'a> TxChannel<'a> {
+    /// Write `data` to the peripheral.
+    ///
+    /// Panics if `data` is longer than `MAX_TRANSFER`.
+    pub fn write(self, data: &'static [u8]) -> Transfer<TxChannel<'a>, &'static [u8]> {
+        let number = self.channel.number;
+        if !data.is_empty() {
+            let register = self.channel.target.data_register();
+            let end = data.as_ptr() as u32 + data.len() as u32 - 1;
+            self.channel
+                .start(end, register, CTL_DSTINC_NONE, data.len());
+        }
+        Transfer {
+            number,
+            parts: Some((self, data)),
+        }
+    }
+
+    /// Call `callback`, from the interrupt, at the end of each transfer.
+    pub fn set_callback(&mut self, callback: Option<fn()>) {
+        self.channel.set_callback(callback);
+    }
+
+    /// Which peripheral this channel writes to.
+    pub fn target(&self) -> Target {
+        self.channel.target
+    }
+}
+
+impl<'a> RxChannel<'a> {
+    /// Fill `buffer` from the peripheral.
+    ///
+    /// Panics if `buffer` is longer than `MAX_TRANSFER`.
+    pub fn read(self, buffer: &'static mut [u8]) -> Transfer<RxChannel<'a>, &'static mut [u8]> {
+        let number = self.channel.number;
+        if !buffer.is_empty() {
+            let register = self.channel.target.data_register();
+            let end = buffer.as_mut_ptr() as u32 + buffer.len() as u32 - 1;
+            self.channel
+                .start(register, end, CTL_SRCINC_NONE, buffer.len());
+        }
+        Transfer {
+            number,
+            parts: Some((self, buffer)),
+        }
+    }
+
+    /// Call `callback`, from the interrupt, at the end of each transfer.
+    pub fn set_callback(&mut self, callback: Option<fn()>) {
+        self.channel.set_callback(callback);
+    }
+
+    /// Which peripheral this channel reads from.
+    pub fn target(&self) -> Target {
+        self.channel.target
+    }
+}
+
+impl<C, B> Transfer<C, B> {
+    /// Has the transfer finished?
+    pub fn is_done(&self) -> bool {
+        registers().enaset.read().bits() & (1 << self.number) == 0
+    }
+
+    /// Wait for the transfer to finish.
+    pub fn wait(mut self) -> (C, B) {
+        while !self.is_done() {}
+        self.finish()
+    }
+
+    /// Take the channel and buffer back, once the transfer is done.
+    fn finish(&mut self) -> (C, B) {
+        stop(self.number);
+        self.parts.take().expect("transfer already finished")
+    }
+}
+
+impl<C, B> Future for Transfer<C, B>
+where
+    C: Unpin,
+    B: Unpin,
+{
+    type Output = (C, B);
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<(C, B)> {
+        if !self.is_done() {
+            let number = self.number;
+            interrupt::free(|cs| {
+                SLOTS.borrow(cs).borrow_mut()[number].waker = Some(cx.waker().clone());
+            });
+            // It may have finished before the waker was in place.
+            if !self.is_done() {
+                return Poll::Pending;
+            }
+        }
+        Poll::Ready(self.finish())
+    }
+}
+
+impl<C, B> Drop for Transfer<C, B> {
+    fn drop(&mut self) {
+        if self.parts.is_some() {
+            stop(self.number);
+        }
+    }
+}
+
+impl<SPI, PINS> DmaSpi<SPI, PINS>
+where
+    Spi<SPI, PINS>: Peripheral,
+{
+    /// Run `spi`'s transfers with DMA.
+    ///
+    /// Panics if its channels are in use (by UART2, if this is SSI2).
+    pub fn new(_dma: &Dma, spi: Spi<SPI, PINS>) -> DmaSpi<SPI, PINS> {
+        let target = <Spi<SPI, PINS> as Peripheral>::TARGET;
+        let tx = Channel::allocate(target, Direction::Tx).expect("SPI DMA channel in use");
+        let rx = Channel::allocate(target, Direction::Rx).expect("SPI DMA channel in use");
+        DmaSpi { spi, tx, rx }
+    }
+
+    /// Give the `Spi` back.
+    pub fn free(self) -> Spi<SPI, PINS> {
+        self.spi
+    }
+
+    /// Send `len` bytes from `source`, putting the replies in `destination`
+    /// - or all in its first byte, if `keep` is false.
+    fn exchange(&mut self, source: *const u8, destination: *mut u8, len: usize, keep: bool) {
+        // Anything left in the receive FIFO isn't ours.
+        let ssi = self.rx.target.ssi();
+        while ssi.sr.read().bits() & SR_RNE != 0 {
+            let _ = ssi.dr.read().bits();
+        }
+        let register = self.rx.target.data_register();
+        let last = len as u32 - 1;
+        let (destination_end, increments) = if keep {
+            (destination as u32 + last, CTL_SRCINC_NONE)
+        } else {
+            (destination as u32, CTL_SRCINC_NONE | CTL_DSTINC_NONE)
+        };
+        // Receive first, so nothing comes back before we're ready for it.
+        self.rx.start(register, destination_end, increments, len);
+        self.tx
+            .start(source as u32 + last, register, CTL_DSTINC_NONE, len);
+        let both = (1 << self.tx.number) | (1 << self.rx.number);
+        while registers().enaset.read().bits() & both != 0 {}
+        stop(self.tx.number);
+        stop(self.rx.number);
+    }
+}
+
+impl<SPI, PINS> spi::Transfer<u8> for DmaSpi<SPI, PINS>
+where
+    Spi<SPI, PINS>: Peripheral,
+{
+    type Error = Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Infallible> {
+        // Each byte goes out before its reply comes back, so sending and
+        // receiving can share the buffer.
+        for chunk in words.chunks_mut(MAX_TRANSFER) {
+            let pointer = chunk.as_mut_ptr();
+            self.exchange(pointer, pointer, chunk.len(), true);
+        }
+        Ok(words)
+    }
+}
+
+impl<SPI, PINS> spi::Write<u8> for DmaSpi<SPI, PINS>
+where
+    Spi<SPI, PINS>: Peripheral,
+{
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+        let mut ignored = 0u8;
+        for chunk in words.chunks(MAX_TRANSFER) {
+            self.exchange(chunk.as_ptr(), &mut ignored, chunk.len(), false);
+        }
+        Ok(())
+    }
+}
+
+/// Tidy up after finished transfers, and wake whoever is waiting for them.
+/// Called from the crate's UART and SSI interrupt handlers with the `dma`
+/// feature - otherwise from the application's.
+pub fn on_interrupt() {
+    let udma = registers();
+    let done = udma.chis.read().bits();
+    if done == 0 {
+        return;
+    }
+    udma.chis.write(|w| unsafe { w.bits(done) });
+    for number in 0..32 {
+        if done & (1 << number) == 0 {
+            continue;
+        }
+        let (waker, callback) = interrupt::free(|cs| {
+            let mut slots = SLOTS.borrow(cs).borrow_mut();
+            let slot = &mut slots[number];
+            if let Some((target, direction)) = slot.running.take() {
+                target.request(direction, false);
+            }
+            (slot.waker.take(), slot.callback)
+        });
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        if let Some(callback) = callback {
+            callback();
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl<'a> TxChannel<'a> {
+    fn allocate(target: Target) -> Result<TxChannel<'a>, Error> {
+        Channel::allocate(target, Direction::Tx).map(|channel| TxChannel {
+            channel,
+            _peripheral: PhantomData,
+        })
+    }
+}
+
+impl<'a> RxChannel<'a> {
+    fn allocate(target: Target) -> Result<RxChannel<'a>, Error> {
+        Channel::allocate(target, Direction::Rx).map(|channel| RxChannel {
+            channel,
+            _peripheral: PhantomData,
+        })
+    }
+}
+
+impl Channel {
+    /// Claim the channel `target` uses in this direction, and route the
+    /// target's requests to it.
+    fn allocate(target: Target, direction: Direction) -> Result<Channel, Error> {
+        let (number, encoding) = target.channel(direction);
+        let bit = 1 << number;
+        if ALLOCATED.fetch_or(bit, Ordering::AcqRel) & bit != 0 {
+            return Err(Error::InUse);
+        }
+        let udma = registers();
+        let shift = (number % 8) * 4;
+        let map = |r: u32| (r & !(0xF << shift)) | (encoding << shift);
+        interrupt::free(|_| match number / 8 {
+            0 => udma.chmap0.modify(|r, w| unsafe { w.bits(map(r.bits())) }),
+            1 => udma.chmap1.modify(|r, w| unsafe { w.bits(map(r.bits())) }),
+            2 => udma.chmap2.modify(|r, w| unsafe { w.bits(map(r.bits())) }),
+            _ => udma.chmap3.modify(|r, w| unsafe { w.bits(map(r.bits())) }),
+        });
+        // Single requests, primary descriptor, normal priority, unmasked.
+        udma.useburstclr.write(|w| unsafe { w.bits(bit) });
+        udma.altclr.write(|w| unsafe { w.bits(bit) });
+        udma.prioclr.write(|w| unsafe { w.bits(bit) });
+        udma.reqmaskclr.write(|w| unsafe { w.bits(bit) });
+        Ok(Channel {
+            number: number as usize,
+            target,
+            direction,
+        })
+    }
+
+    /// Move `len` bytes, one request at a time.
+    fn start(&self, source_end: u32, destination_end: u32, increments: u32, len: usize) {
+        assert!(len <= MAX_TRANSFER);
+        let descriptor = Descriptor {
+            source_end,
+            destination_end,
+            control: increments | ((len as u32 - 1) << CTL_XFERSIZE_SHIFT) | CTL_XFERMODE_BASIC,
+            _unused: 0,
+        };
+        unsafe {
+            let table = ptr::addr_of_mut!(CONTROL_TABLE) as *mut Descriptor;
+            ptr::write_volatile(table.add(self.number), descriptor);
+        }
+        // The buffer must be in memory before the controller reads it.
+        atomic::fence(Ordering::SeqCst);
+        interrupt::free(|cs| {
+            SLOTS.borrow(cs).borrow_mut()[self.number].running =
+                Some((self.target, self.direction));
+        });
+        registers()
+            .enaset
+            .write(|w| unsafe { w.bits(1 << self.number) });
+        self.target.request(self.direction, true);
+        unsafe { NVIC::unmask(self.target.interrupt()) };
+    }
+
+    fn set_callback(&mut self, callback: Option<fn()>) {
+        interrupt::free(|cs| SLOTS.borrow(cs).borrow_mut()[self.number].callback = callback);
+    }
+}
+
+impl Drop for Channel {
+    fn drop(&mut self) {
+        interrupt::free(|cs| SLOTS.borrow(cs).borrow_mut()[self.number].callback = None);
+        ALLOCATED.fetch_and(!(1 << self.number), Ordering::AcqRel);
+    }
+}
+
+impl<TX, RX, RTS, CTS> Peripheral for Serial<tm4c123x::UART0, TX, RX, RTS, CTS> {
+    const TARGET: Target = Target::Uart0;
+}
+
+impl<TX, RX, RTS, CTS> Peripheral for Serial<tm4c123x::UART1, TX, RX, RTS, CTS> {
+    const TARGET: Target = Target::Uart1;
+}
+
+impl<TX, RX, RTS, CTS> Peripheral for Serial<tm4c123x::UART2, TX, RX, RTS, CTS> {
+    const TARGET: Target = Target::Uart2;
+}
+
+impl<PINS> Peripheral for Spi<tm4c123x::SSI0, PINS> {
+    const TARGET: Target = Target::Ssi0;
+}
+
+impl<PINS> Peripheral for Spi<tm4c123x::SSI1, PINS> {
+    const TARGET: Target = Target::Ssi1;
+}
+
+impl<PINS> Peripheral for Spi<tm4c123x::SSI2, PINS> {
+    const TARGET: Target = Target::Ssi2;
+}
+
+impl<PINS> Peripheral for Spi<tm4c123x::SSI3, PINS> {
+    const TARGET: Target = Target::Ssi3;
+}
+
+impl Target {
+    /// The channel number and channel map encoding for each direction.
+    fn channel(self, direction: Direction) -> (u32, u32) {
+        let (rx, encoding) = match self {
+            Target::Uart0 => (8, 0),
+            Target::Uart1 => (22, 0),
+            Target::Uart2 => (12, 1),
+            Target::Ssi0 => (10, 0),
+            Target::Ssi1 => (24, 0),
+            Target::Ssi2 => (12, 2),
+            Target::Ssi3 => (14, 2),
+        };
+        match direction {
+            Direction::Rx => (rx, encoding),
+            Direction::Tx => (rx + 1, encoding),
+        }
+    }
+
+    fn interrupt(self) -> Interrupt {
+        match self {
+            Target::Uart0 => Interrupt::UART0,
+            Target::Uart1 => Interrupt::UART1,
+            Target::Uart2 => Interrupt::UART2,
+            Target::Ssi0 => Interrupt::SSI0,
+            Target::Ssi1 => Interrupt::SSI1,
+            Target::Ssi2 => Interrupt::SSI2,
+            Target::Ssi3 => Interrupt::SSI3,
+        }
+    }
+
+    fn uart(self) -> Option<Port> {
+        match self {
+            Target::Uart0 => Some(Port::Uart0),
+            Target::Uart1 => Some(Port::Uart1),
+            Target::Uart2 => Some(Port::Uart2),
+            _ => None,
+        }
+    }
+
+    fn ssi(self) -> &'static ssi0::RegisterBlock {
+        unsafe {
+            match self {
+                Target::Ssi1 => &*tm4c123x::SSI1::ptr(),
+                Target::Ssi2 => &*tm4c123x::SSI2::ptr(),
+                Target::Ssi3 => &*tm4c123x::SSI3::ptr(),
+                _ => &*tm4c123x::SSI0::ptr(),
+            }
+        }
+    }
+
+    /// The address of the peripheral's data register.
+    fn data_register(self) -> u32 {
+        match self.uart() {
+            Some(port) => &port.registers().dr as *const _ as u32,
+            None => &self.ssi().dr as *const _ as u32,
+        }
+    }
+
+    /// Let the peripheral ask for DMA in this direction, or stop it.
+    fn request(self, direction: Direction, enable: bool) {
+        let bit = match direction {
+            Direction::Rx => DMACTL_RXDMAE,
+            Direction::Tx => DMACTL_TXDMAE,
+        };
+        let update = |bits: u32| if enable { bits | bit } else { bits & !bit };
+        interrupt::free(|_| match self.uart() {
+            Some(port) => port
+                .registers()
+                .dmactl
+                .modify(|r, w| unsafe { w.bits(update(r.bits())) }),
+            None => self
+                .ssi()
+                .dmactl
+                .modify(|r, w| unsafe { w.bits(update(r.bits())) }),
+        });
+    }
+}
+
+/// Stop a channel (if it's still going) and its peripheral's requests, and
+/// make sure we see what it wrote.
+fn stop(number: usize) {
+    registers().enaclr.write(|w| unsafe { w.bits(1 << number) });
+    interrupt::free(|cs| {
+        let mut slots = SLOTS.borrow(cs).borrow_mut();
+        let slot = &mut slots[number];
+        if let Some((target, direction)) = slot.running.take() {
+            target.request(direction, false);
+        }
+        slot.waker = None;
+    });
+    atomic::fence(Ordering::SeqCst);
+}
+
+fn registers() -> &'static udma::RegisterBlock {
+    unsafe { &*UDMA::ptr() }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,298 @@
+//! Edge interrupts from the two switches (PF4 and PF0), through the GPIOF
+//! interrupt.
+//!
+//! `listen` arms a switch. When it fires, the GPIOF handler (the crate's,
+//! with the `edges` feature, or the application's calling `on_interrupt`)
+//! acknowledges it, marks the switch as pending and calls the function
+//! registered with `set_handler`, if any. `wait_for_press` puts the core to
+//! sleep until a switch is pressed, so the buttons can wake an otherwise idle
+//! application. In `async` code, `edge` is a future for the next edge on a
+//! switch (see `common::executor`).
+//!
+//! This only touches the interrupt registers of port F, so it works whoever
+//! owns the pins - the `Board`, a `Buttons` or the application.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::cell::{Cell, RefCell};
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU8, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use cortex_m::interrupt::{self, Mutex};
+use cortex_m::peripheral::NVIC;
+use cortex_m::register;
+use tm4c123x_hal::tm4c123x::{gpio_porta, Interrupt, GPIO_PORTF};
+
+use super::Button;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Which edges of a switch cause an interrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// When the switch goes down (a falling edge, as it shorts the pin to
+    /// ground).
+    Press,
+    /// When the switch comes back up.
+    Release,
+    /// Both.
+    Both,
+}
+
+/// A future which completes at the next edge on a switch. See `edge`.
+#[derive(Debug)]
+pub struct Edge {
+    button: Button,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// Called from the interrupt handler, with the switch that fired.
+static HANDLER: Mutex<Cell<Option<fn(Button)>>> = Mutex::new(Cell::new(None));
+
+/// Pin bits of the switches that have fired and not yet been collected.
+static PENDING: AtomicU8 = AtomicU8::new(0);
+
+/// The tasks waiting on an `Edge`, for SW1 and SW2.
+static WAKERS: Mutex<RefCell<[Option<Waker>; 2]>> = Mutex::new(RefCell::new([NO_WAKER; 2]));
+
+const NO_WAKER: Option<Waker> = None;
+
+const SW1_PIN: u32 = 1 << 4;
+const SW2_PIN: u32 = 1 << 0;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Interrupt on the given edges of a switch.
+pub fn listen(button: Button, trigger: Trigger) {
+    let pin = pin(button);
+    interrupt::free(|_| {
+        let port = port();
+        // Mask it while we reconfigure, so it can't fire spuriously.
+        port.im.modify(|r, w| unsafe { w.bits(r.bits() & !pin) });
+        // Edge, not level, sensitive.
+        port.is.modify(|r, w| unsafe { w.bits(r.bits() & !pin) });
+        match trigger {
+            Trigger::Both => port.ibe.modify(|r, w| unsafe { w.bits(r.bits() | pin) }),
+            Trigger::Press => {
+                port.ibe.modify(|r, w| unsafe { w.bits(r.bits() & !pin) });
+                port.iev.modify(|r, w| unsafe { w.bits(r.bits() & !pin) });
+            }
+            Trigger::Release => {
+                port.ibe.modify(|r, w| unsafe { w.bits(r.bits() & !pin) });
+                port.iev.modify(|r, w| unsafe { w.bits(r.bits() | pin) });
+            }
+        }
+        port.icr.write(|w| unsafe { w.bits(pin) });
+        port.im.modify(|r, w| unsafe { w.bits(r.bits() | pin) });
+    });
+    unsafe { NVIC::unmask(Interrupt::GPIOF) };
+}
+
+/// Stop interrupting on a switch.
+pub fn unlisten(button: Button) {
+    let pin = pin(button);
+    interrupt::free(|_| {
+        port().im.modify(|r, w| unsafe { w.bits(r.bits() & !pin) });
+    });
+}
+
+/// Call `handler` (in interrupt context) whenever an armed switch fires.
+/// Replaces any handler already set.
+pub fn set_handler(handler: fn(Button)) {
+    interrupt::free(|cs| HANDLER.borrow(cs).set(Some(handler)));
+}
+
+/// Stop calling the handler.
+pub fn clear_handler() {
+    interrupt::free(|cs| HANDLER.borrow(cs).set(None));
+}
+
+/// Collect a switch which has fired since it was last collected, if any.
+pub fn take_pending() -> Option<Button> {
+    for &(button, pin) in &[(Button::One, SW1_PIN), (Button::Two, SW2_PIN)] {
+        let bit = pin as u8;
+        if PENDING.fetch_and(!bit, Ordering::Relaxed) & bit != 0 {
+            return Some(button);
+        }
+    }
+    None
+}
+
+/// Sleep until either switch is pressed, and say which. Presses from before
+/// the call don't count. Both switches are armed for `Trigger::Press` while
+/// it waits, and then put back as they were. It can be called with
+/// interrupts masked (in a critical section, say), and leaves them that way.
+pub fn wait_for_press() -> Button {
+    let pins = SW1_PIN | SW2_PIN;
+    let saved = interrupt::free(|_| {
+        let port = port();
+        [
+            port.im.read().bits(),
+            port.is.read().bits(),
+            port.ibe.read().bits(),
+            port.iev.read().bits(),
+        ]
+    });
+    PENDING.fetch_and(!(pins as u8), Ordering::Relaxed);
+    listen(Button::One, Trigger::Press);
+    listen(Button::Two, Trigger::Press);
+    let primask = register::primask::read();
+    let button = loop {
+        // With interrupts masked, the interrupt can't slip in between the
+        // check and the WFI. WFI still wakes on it, and it runs as soon as
+        // we unmask.
+        interrupt::disable();
+        if let Some(button) = take_pending() {
+            break button;
+        }
+        cortex_m::asm::wfi();
+        if primask.is_active() {
+            unsafe { interrupt::enable() };
+        } else if NVIC::is_pending(Interrupt::GPIOF) {
+            // They were masked by our caller, so the handler can't run. Do
+            // its job here instead.
+            NVIC::unpend(Interrupt::GPIOF);
+            on_interrupt();
+        }
+    };
+    interrupt::free(|_| {
+        let port = port();
+        port.im.modify(|r, w| unsafe { w.bits(r.bits() & !pins) });
+        port.is
+            .modify(|r, w| unsafe { w.bits((r.bits() & !pins) | (saved[1] & pins)) });
+        port.ibe
+            .modify(|r, w| unsafe { w.bits((r.bits() & !pins) | (saved[2] & pins)) });
+        port.iev
+            .modify(|r, w| unsafe { w.bits((r.bits() & !pins) | (saved[3] & pins)) });
+        port.icr.write(|w| unsafe { w.bits(pins) });
+        port.im
+            .modify(|r, w| unsafe { w.bits(r.bits() | (saved[0] & pins)) });
+    });
+    if primask.is_active() {
+        unsafe { interrupt::enable() };
+    }
+    button
+}
+
+/// A future which arms `button` for `trigger` and completes at the next such
+/// edge. Edges from before it was created don't count. It collects the
+/// switch's pending flag, so don't mix it with `take_pending` on the same
+/// switch.
+pub fn edge(button: Button, trigger: Trigger) -> Edge {
+    PENDING.fetch_and(!(pin(button) as u8), Ordering::Relaxed);
+    listen(button, trigger);
+    Edge { button }
+}
+
+impl Future for Edge {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let bit = pin(self.button) as u8;
+        // Checked and parked in one go, so an edge can't be missed between.
+        interrupt::free(|cs| {
+            if PENDING.fetch_and(!bit, Ordering::Relaxed) & bit != 0 {
+                Poll::Ready(())
+            } else {
+                WAKERS.borrow(cs).borrow_mut()[index(self.button)] = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+    }
+}
+
+/// Acknowledge the switches that fired, and tell whoever's interested.
+/// Called from the crate's GPIOF interrupt handler with the `edges`
+/// feature - otherwise from the application's.
+pub fn on_interrupt() {
+    let port = port();
+    let fired = port.mis.read().bits() & (SW1_PIN | SW2_PIN);
+    port.icr.write(|w| unsafe { w.bits(fired) });
+    PENDING.fetch_or(fired as u8, Ordering::Relaxed);
+    let (handler, wakers) = interrupt::free(|cs| {
+        let mut wakers = WAKERS.borrow(cs).borrow_mut();
+        let mut woken = [NO_WAKER; 2];
+        for &button in &[Button::One, Button::Two] {
+            if fired & pin(button) != 0 {
+                woken[index(button)] = wakers[index(button)].take();
+            }
+        }
+        (HANDLER.borrow(cs).get(), woken)
+    });
+    for waker in wakers.iter().flatten() {
+        waker.wake_by_ref();
+    }
+    if let Some(handler) = handler {
+        if fired & SW1_PIN != 0 {
+            handler(Button::One);
+        }
+        if fired & SW2_PIN != 0 {
+            handler(Button::Two);
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn pin(button: Button) -> u32 {
+    match button {
+        Button::One => SW1_PIN,
+        Button::Two => SW2_PIN,
+    }
+}
+
+fn index(button: Button) -> usize {
+    match button {
+        Button::One => 0,
+        Button::Two => 1,
+    }
+}
+
+fn port() -> &'static gpio_porta::RegisterBlock {
+    unsafe { &*GPIO_PORTF::ptr() }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
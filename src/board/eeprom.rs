@@ -0,0 +1,158 @@
+//! Minimal word-level access to the on-chip EEPROM, as needed to keep a copy
+//! of the crash record. The EEPROM is 2 KiB, organised as 32 blocks of 16
+//! 32-bit words; we address it by word.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use tm4c123x_hal::sysctl::{self, Domain, PowerControl, PowerState, RunMode};
+use tm4c123x_hal::tm4c123x::EEPROM;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Things that can go wrong talking to the EEPROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EepromError {
+    /// The EEPROM's power-on recovery failed - see the errata, and the
+    /// datasheet's description of EESUPP.
+    RecoveryFailed,
+    /// A write was refused, or failed.
+    WriteFailed,
+    /// The address is beyond the end of the EEPROM.
+    OutOfRange,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The size of the EEPROM, in 32-bit words.
+pub const EEPROM_WORDS: usize = 512;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const WORDS_PER_BLOCK: usize = 16;
+
+// EEDONE
+const EEDONE_WORKING: u32 = 1 << 0;
+const EEDONE_ERRORS: u32 = 0x3C;
+
+// EESUPP
+const EESUPP_ERETRY: u32 = 1 << 2;
+const EESUPP_PRETRY: u32 = 1 << 3;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Power up the EEPROM and run the initialisation sequence from the
+/// datasheet. Safe to call more than once.
+pub(crate) fn init(eeprom: &EEPROM, power_control: &PowerControl) -> Result<(), EepromError> {
+    sysctl::control_power(power_control, Domain::Eeprom, RunMode::Run, PowerState::On);
+    // The datasheet asks for six cycles before we touch the registers.
+    cortex_m::asm::delay(6);
+    wait_done(eeprom);
+    check_recovery(eeprom)?;
+    sysctl::reset(power_control, Domain::Eeprom);
+    cortex_m::asm::delay(6);
+    wait_done(eeprom);
+    check_recovery(eeprom)
+}
+
+/// Read `data.len()` words, starting at word `address`.
+pub(crate) fn read(eeprom: &EEPROM, address: usize, data: &mut [u32]) -> Result<(), EepromError> {
+    check_range(address, data.len())?;
+    for (i, word) in data.iter_mut().enumerate() {
+        select(eeprom, address + i);
+        *word = eeprom.eerdwr.read().bits();
+    }
+    Ok(())
+}
+
+/// Write `data.len()` words, starting at word `address`. Words that already
+/// hold the right value are skipped, to save wear.
+pub(crate) fn write(eeprom: &EEPROM, address: usize, data: &[u32]) -> Result<(), EepromError> {
+    check_range(address, data.len())?;
+    for (i, word) in data.iter().enumerate() {
+        select(eeprom, address + i);
+        if eeprom.eerdwr.read().bits() == *word {
+            continue;
+        }
+        eeprom.eerdwr.write(|w| unsafe { w.bits(*word) });
+        if wait_done(eeprom) & EEDONE_ERRORS != 0 {
+            return Err(EepromError::WriteFailed);
+        }
+    }
+    Ok(())
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn check_range(address: usize, len: usize) -> Result<(), EepromError> {
+    if address
+        .checked_add(len)
+        .map_or(true, |end| end > EEPROM_WORDS)
+    {
+        Err(EepromError::OutOfRange)
+    } else {
+        Ok(())
+    }
+}
+
+fn check_recovery(eeprom: &EEPROM) -> Result<(), EepromError> {
+    if eeprom.eesupp.read().bits() & (EESUPP_ERETRY | EESUPP_PRETRY) != 0 {
+        Err(EepromError::RecoveryFailed)
+    } else {
+        Ok(())
+    }
+}
+
+fn select(eeprom: &EEPROM, address: usize) {
+    let block = (address / WORDS_PER_BLOCK) as u32;
+    let offset = (address % WORDS_PER_BLOCK) as u32;
+    eeprom.eeblock.write(|w| unsafe { w.bits(block) });
+    eeprom.eeoffset.write(|w| unsafe { w.bits(offset) });
+}
+
+/// Spin until the EEPROM is idle, returning the final EEDONE value.
+fn wait_done(eeprom: &EEPROM) -> u32 {
+    loop {
+        let done = eeprom.eedone.read().bits();
+        if done & EEDONE_WORKING == 0 {
+            return done;
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,181 @@
+//! Non-blocking LED effects, run from the TIMER2A interrupt.
+//!
+//! Hand an `RgbPwm` and TIMER2 to `start`, then `play` an `Effect`. The
+//! timer interrupts every `TICK_MS` milliseconds and the interrupt handler
+//! shows `Effect::colour_at` for that moment, so the application can carry
+//! on with something else. The effects themselves live in the `colour`
+//! crate, where their timing is tested.
+//!
+//! The crate only binds TIMER2A with the `effects` feature. Without it,
+//! the application's TIMER2A handler must call `on_interrupt`.
+//!
+//! ```ignore
+//! let led = RgbLed::new(board.led_red, board.led_green, board.led_blue)
+//!     .into_pwm(board.TIMER0, board.TIMER1, &mut board.portf_control, &board.power_control);
+//! effects::start(led, board.TIMER2, &board.power_control);
+//! effects::play(Effect::Morse {
+//!     colour: Rgb::new(0, 0, 255),
+//!     message: "SOS",
+//!     unit_ms: 150,
+//!     repeat: true,
+//! });
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::cell::RefCell;
+
+use cortex_m::interrupt::{self, Mutex};
+use cortex_m::peripheral::NVIC;
+use tm4c123x_hal::sysctl::{self, Domain, PowerControl, PowerState, RunMode};
+use tm4c123x_hal::tm4c123x::{Interrupt, TIMER2};
+
+use crate::colour::BLACK;
+
+use super::{clocks, gptm, RgbPwm};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+pub use crate::colour::effect::{Effect, Step};
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// How often the effect is updated, in milliseconds.
+pub const TICK_MS: u32 = 10;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+struct Engine {
+    led: RgbPwm,
+    timer: TIMER2,
+    effect: Effect,
+    elapsed_ms: u32,
+    finished: bool,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+static ENGINE: Mutex<RefCell<Option<Engine>>> = Mutex::new(RefCell::new(None));
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Take over the LED and TIMER2, and start ticking. The LED is off until
+/// something is played. Call after `Board::new` has set the clocks.
+pub fn start(led: RgbPwm, timer: TIMER2, power_control: &PowerControl) {
+    sysctl::control_power(power_control, Domain::Timer2, RunMode::Run, PowerState::On);
+    sysctl::reset(power_control, Domain::Timer2);
+    let ticks = clocks().sysclk.0 / 1000 * TICK_MS;
+    interrupt::free(|cs| {
+        let mut led = led;
+        led.set_rgb(BLACK);
+        gptm::start_periodic(&timer, ticks);
+        *ENGINE.borrow(cs).borrow_mut() = Some(Engine {
+            led,
+            timer,
+            effect: Effect::Off,
+            elapsed_ms: 0,
+            finished: false,
+        });
+    });
+    unsafe { NVIC::unmask(Interrupt::TIMER2A) };
+}
+
+/// Stop ticking, and hand back the LED and timer. The LED is left showing
+/// whatever it was showing.
+pub fn stop() -> Option<(RgbPwm, TIMER2)> {
+    NVIC::mask(Interrupt::TIMER2A);
+    interrupt::free(|cs| {
+        ENGINE.borrow(cs).borrow_mut().take().map(|engine| {
+            gptm::stop(&engine.timer);
+            (engine.led, engine.timer)
+        })
+    })
+}
+
+/// Replace whatever's playing with `effect`, from its beginning. Does
+/// nothing if the engine hasn't been started.
+pub fn play(effect: Effect) {
+    interrupt::free(|cs| {
+        if let Some(ref mut engine) = *ENGINE.borrow(cs).borrow_mut() {
+            engine.effect = effect;
+            engine.elapsed_ms = 0;
+            engine.finished = false;
+            engine.show();
+        }
+    });
+}
+
+/// Has the current effect run to its end? Only a non-repeating `Morse` or
+/// `Sequence` ever does. The LED is switched off when it finishes.
+pub fn is_finished() -> bool {
+    interrupt::free(|cs| {
+        ENGINE
+            .borrow(cs)
+            .borrow()
+            .as_ref()
+            .map_or(true, |engine| engine.finished)
+    })
+}
+
+/// Advance the current effect by one tick. Called from the crate's TIMER2A
+/// interrupt handler with the `effects` feature - otherwise from the
+/// application's.
+pub fn on_interrupt() {
+    interrupt::free(|cs| {
+        if let Some(ref mut engine) = *ENGINE.borrow(cs).borrow_mut() {
+            gptm::clear_timeout(&engine.timer);
+            if !engine.finished {
+                engine.elapsed_ms = engine.elapsed_ms.wrapping_add(TICK_MS);
+                engine.show();
+            }
+        }
+    });
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl Engine {
+    fn show(&mut self) {
+        match self.effect.colour_at(self.elapsed_ms) {
+            Some(colour) => self.led.set_rgb(colour),
+            None => {
+                self.finished = true;
+                self.led.set_rgb(BLACK);
+            }
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,241 @@
+//! Blink codes for a board that has stopped, readable without a serial cable.
+//!
+//! The colour says which class of failure it was and the number of blinks
+//! says which one:
+//!
+//! | Colour  | Blinks | Failure                            |
+//! |---------|--------|------------------------------------|
+//! | Red     | 1      | Panic                              |
+//! | Red     | 2      | Out of memory                      |
+//! | Red     | 3      | `main()` returned an error         |
+//! | Magenta | 1      | HardFault                          |
+//! | Magenta | 2      | MemManage fault                    |
+//! | Magenta | 3      | BusFault                           |
+//! | Magenta | 4      | UsageFault                         |
+//! | Yellow  | 1      | Unexpected interrupt (see below)   |
+//! | White   | 1      | Unknown                            |
+//!
+//! For an unexpected interrupt, the IRQ number follows as decimal digits in
+//! blue, most significant first. A digit is that many short blinks, with
+//! zero shown as a single long blink. If it was a system exception rather
+//! than an IRQ, its exception number is shown in green instead.
+//!
+//! The whole sequence repeats after a long pause.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::digital::v2::OutputPin;
+use tm4c123x_hal::delay::Delay;
+use tm4c123x_hal::gpio::gpiof::{PF1, PF2, PF3};
+use tm4c123x_hal::gpio::{GpioExt, Output, PushPull};
+use tm4c123x_hal::sysctl::SysctlExt;
+
+use super::crash::CrashKind;
+use super::fault_clocks;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// Which of the three LEDs to light.
+#[derive(Clone, Copy)]
+struct Colour {
+    red: bool,
+    green: bool,
+    blue: bool,
+}
+
+/// The LEDs, stolen from whoever had them.
+struct Leds {
+    red: PF1<Output<PushPull>>,
+    blue: PF2<Output<PushPull>>,
+    green: PF3<Output<PushPull>>,
+    delay: Delay,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const RED: Colour = Colour {
+    red: true,
+    green: false,
+    blue: false,
+};
+const GREEN: Colour = Colour {
+    red: false,
+    green: true,
+    blue: false,
+};
+const BLUE: Colour = Colour {
+    red: false,
+    green: false,
+    blue: true,
+};
+const MAGENTA: Colour = Colour {
+    red: true,
+    green: false,
+    blue: true,
+};
+const YELLOW: Colour = Colour {
+    red: true,
+    green: true,
+    blue: false,
+};
+const WHITE: Colour = Colour {
+    red: true,
+    green: true,
+    blue: true,
+};
+const OFF: Colour = Colour {
+    red: false,
+    green: false,
+    blue: false,
+};
+
+const SHORT_ON_MS: u32 = 200;
+const LONG_ON_MS: u32 = 800;
+const BLINK_OFF_MS: u32 = 300;
+const DIGIT_GAP_MS: u32 = 1000;
+const REPEAT_GAP_MS: u32 = 2500;
+
+/// Exception numbers below this are system exceptions; cortex-m-rt passes
+/// `DefaultHandler` the IRQ number, which is the exception number less this.
+const FIRST_IRQ_EXCEPTION: i32 = 16;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Blink the code for `kind` (with `detail` as recorded in the crash
+/// record) on the LEDs, forever.
+pub fn halt_with_code(kind: CrashKind, detail: u32) -> ! {
+    let (colour, blinks) = class(kind);
+    let mut leds = Leds::steal();
+    loop {
+        for _ in 0..blinks {
+            leds.blink(colour, SHORT_ON_MS);
+        }
+        if kind == CrashKind::UnexpectedInterrupt {
+            let irq = detail as i16 as i32;
+            if irq >= 0 {
+                leds.number(BLUE, irq as u32);
+            } else {
+                leds.number(GREEN, (irq + FIRST_IRQ_EXCEPTION) as u32);
+            }
+        }
+        leds.delay.delay_ms(REPEAT_GAP_MS);
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn class(kind: CrashKind) -> (Colour, u32) {
+    match kind {
+        CrashKind::Panic => (RED, 1),
+        CrashKind::OutOfMemory => (RED, 2),
+        CrashKind::MainReturnedError => (RED, 3),
+        CrashKind::HardFault => (MAGENTA, 1),
+        CrashKind::MemoryManagement => (MAGENTA, 2),
+        CrashKind::BusFault => (MAGENTA, 3),
+        CrashKind::UsageFault => (MAGENTA, 4),
+        CrashKind::UnexpectedInterrupt => (YELLOW, 1),
+        CrashKind::Unknown => (WHITE, 1),
+    }
+}
+
+impl Leds {
+    fn steal() -> Leds {
+        let core_peripherals = unsafe { tm4c123x_hal::CorePeripherals::steal() };
+        let p = unsafe { tm4c123x_hal::Peripherals::steal() };
+        let pins = p.GPIO_PORTF.split(&p.SYSCTL.constrain().power_control);
+        Leds {
+            red: pins.pf1.into_push_pull_output(),
+            blue: pins.pf2.into_push_pull_output(),
+            green: pins.pf3.into_push_pull_output(),
+            delay: Delay::new(core_peripherals.SYST, fault_clocks()),
+        }
+    }
+
+    fn set(&mut self, colour: Colour) {
+        let _ = if colour.red {
+            self.red.set_high()
+        } else {
+            self.red.set_low()
+        };
+        let _ = if colour.green {
+            self.green.set_high()
+        } else {
+            self.green.set_low()
+        };
+        let _ = if colour.blue {
+            self.blue.set_high()
+        } else {
+            self.blue.set_low()
+        };
+    }
+
+    fn blink(&mut self, colour: Colour, on_ms: u32) {
+        self.set(colour);
+        self.delay.delay_ms(on_ms);
+        self.set(OFF);
+        self.delay.delay_ms(BLINK_OFF_MS);
+    }
+
+    /// Show `value` in decimal, a digit at a time.
+    fn number(&mut self, colour: Colour, value: u32) {
+        let mut divisor = 1;
+        while value / divisor >= 10 {
+            divisor *= 10;
+        }
+        while divisor > 0 {
+            self.delay.delay_ms(DIGIT_GAP_MS);
+            match (value / divisor) % 10 {
+                0 => self.blink(colour, LONG_ON_MS),
+                digit => {
+                    for _ in 0..digit {
+                        self.blink(colour, SHORT_ON_MS);
+                    }
+                }
+            }
+            divisor /= 10;
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
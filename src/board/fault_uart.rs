@@ -0,0 +1,114 @@
+//! A UART0 writer for panic and fault handlers.
+//!
+//! It re-initialises UART0 and PA0/PA1 with the console's own set-up (see
+//! `console::configure`), so it works whatever state the application left
+//! them in. It doesn't allocate, and it gives up on a byte if the UART won't
+//! take it within a bounded time, so a wedged UART can't stop a handler from
+//! reaching the LED pattern.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::fmt;
+
+use tm4c123x_hal::sysctl::SysctlExt;
+
+use super::{console, fault_clocks};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// UART0, set up for reporting a failure. See the module documentation.
+pub(crate) struct FaultUart {
+    budget: usize,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// How many times we poll the UART for space before dropping a byte. A byte
+/// takes ~87us at 115200 bps, which is a few hundred polls even at 80 MHz.
+const SPINS_PER_BYTE: u32 = 20_000;
+
+/// The most bytes we'll send through one `FaultUart`.
+const MAX_BYTES: usize = 2048;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl FaultUart {
+    /// (Re-)initialise UART0 on PA0/PA1 at 115200 bps, 8N1.
+    pub(crate) fn new() -> FaultUart {
+        let p = unsafe { tm4c123x_hal::Peripherals::steal() };
+        let sysctl = p.SYSCTL.constrain();
+        console::configure(&sysctl.power_control, fault_clocks().sysclk.0);
+        FaultUart { budget: MAX_BYTES }
+    }
+
+    fn write_byte(&mut self, byte: u8) -> fmt::Result {
+        if self.budget == 0 {
+            return Err(fmt::Error);
+        }
+        self.budget -= 1;
+        for _ in 0..SPINS_PER_BYTE {
+            if console::try_write_byte(byte) {
+                return Ok(());
+            }
+        }
+        Err(fmt::Error)
+    }
+}
+
+impl fmt::Write for FaultUart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            if byte == b'\n' {
+                self.write_byte(b'\r')?;
+            }
+            self.write_byte(byte)?;
+        }
+        Ok(())
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
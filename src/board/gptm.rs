@@ -0,0 +1,106 @@
+//! Just enough of the General-Purpose Timer Module to run a 32-bit timer as a
+//! periodic or one-shot interrupt source, for the crate's own services.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use tm4c123x_hal::tm4c123x::timer0;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+// GPTMCFG
+const CFG_32_BIT: u32 = 0x0;
+
+// GPTMTAMR
+const TAMR_ONE_SHOT: u32 = 0x1;
+const TAMR_PERIODIC: u32 = 0x2;
+
+// GPTMCTL
+const CTL_TAEN: u32 = 1 << 0;
+const CTL_TASTALL: u32 = 1 << 1;
+
+// GPTMIMR, GPTMRIS and GPTMICR
+const INT_TATO: u32 = 1 << 0;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Interrupt every `ticks` system clock ticks, until stopped.
+pub(crate) fn start_periodic(timer: &timer0::RegisterBlock, ticks: u32) {
+    start(timer, TAMR_PERIODIC, ticks);
+}
+
+/// Interrupt once, `ticks` system clock ticks from now.
+pub(crate) fn start_one_shot(timer: &timer0::RegisterBlock, ticks: u32) {
+    start(timer, TAMR_ONE_SHOT, ticks);
+}
+
+/// Stop the timer, and discard any pending timeout.
+pub(crate) fn stop(timer: &timer0::RegisterBlock) {
+    timer.ctl.write(|w| unsafe { w.bits(0) });
+    timer.imr.write(|w| unsafe { w.bits(0) });
+    clear_timeout(timer);
+}
+
+/// Acknowledge a timeout. Call from the interrupt handler.
+pub(crate) fn clear_timeout(timer: &timer0::RegisterBlock) {
+    timer.icr.write(|w| unsafe { w.bits(INT_TATO) });
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn start(timer: &timer0::RegisterBlock, mode: u32, ticks: u32) {
+    stop(timer);
+    timer.cfg.write(|w| unsafe { w.bits(CFG_32_BIT) });
+    timer.tamr.write(|w| unsafe { w.bits(mode) });
+    timer.tailr.write(|w| unsafe { w.bits(ticks.max(1) - 1) });
+    timer.imr.write(|w| unsafe { w.bits(INT_TATO) });
+    // Stall with the CPU when a debugger halts it, so time doesn't run on.
+    timer
+        .ctl
+        .write(|w| unsafe { w.bits(CTL_TAEN | CTL_TASTALL) });
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
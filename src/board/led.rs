@@ -0,0 +1,301 @@
+//! Drivers for the Launchpad's tri-colour LED (PF1 red, PF2 blue, PF3
+//! green).
+//!
+//! `RgbLed` switches each colour fully on or off. `RgbPwm` dims each colour
+//! with a hardware PWM, using the timer channel wired to each pin - TIMER0 B
+//! (T0CCP1) for red, TIMER1 A (T1CCP0) for blue and TIMER1 B (T1CCP1) for
+//! green. You can move between the two, and hand the pins back, at any time.
+//!
+//! `RgbPwm` gamma-corrects by default, so brightness steps look even, and
+//! scales each channel by a calibration colour, as the green LED is much
+//! brighter than the other two. The colour maths lives in the `colour`
+//! crate so it can be tested on the host.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use embedded_hal::digital::v2::{OutputPin, StatefulOutputPin};
+use tm4c123x_hal::gpio::gpiof::{GpioControl, PF1, PF2, PF3};
+use tm4c123x_hal::gpio::{AlternateFunction, Output, PushPull, AF7};
+use tm4c123x_hal::sysctl::{self, Domain, PowerControl, PowerState, RunMode};
+use tm4c123x_hal::tm4c123x::{timer0, TIMER0, TIMER1};
+
+use crate::colour::{self, Rgb};
+
+use super::Led;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// The tri-colour LED, with each colour either on or off.
+pub struct RgbLed {
+    red: PF1<Output<PushPull>>,
+    green: PF3<Output<PushPull>>,
+    blue: PF2<Output<PushPull>>,
+}
+
+/// The tri-colour LED, with each colour dimmed by a hardware PWM.
+pub struct RgbPwm {
+    timer0: TIMER0,
+    timer1: TIMER1,
+    red: PF1<AlternateFunction<AF7, PushPull>>,
+    green: PF3<AlternateFunction<AF7, PushPull>>,
+    blue: PF2<AlternateFunction<AF7, PushPull>>,
+    calibration: Rgb,
+    gamma: bool,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The calibration `RgbPwm` starts with. The green LED is about twice as
+/// bright as the red and blue ones.
+pub const DEFAULT_CALIBRATION: Rgb = Rgb {
+    red: 255,
+    green: 128,
+    blue: 255,
+};
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// Each general-purpose timer has two 16-bit halves.
+#[derive(Clone, Copy)]
+enum Half {
+    A,
+    B,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// The PWM period, in system clock ticks - 5 kHz at 80 MHz.
+const PERIOD: u32 = 16_000;
+
+// GPTMCFG
+const CFG_16_BIT: u32 = 0x4;
+
+// GPTMTnMR
+const MR_PERIODIC: u32 = 0x2;
+const MR_AMS: u32 = 1 << 3;
+
+// GPTMCTL
+const CTL_TAEN: u32 = 1 << 0;
+const CTL_TBEN: u32 = 1 << 8;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl RgbLed {
+    /// Take the three LED pins, and switch them all off.
+    pub fn new(
+        red: PF1<Output<PushPull>>,
+        green: PF3<Output<PushPull>>,
+        blue: PF2<Output<PushPull>>,
+    ) -> RgbLed {
+        let mut led = RgbLed { red, green, blue };
+        led.set_color(false, false, false);
+        led
+    }
+
+    /// Switch one colour on or off.
+    pub fn set(&mut self, led: Led, on: bool) {
+        match led {
+            Led::Red => set_pin(&mut self.red, on),
+            Led::Green => set_pin(&mut self.green, on),
+            Led::Blue => set_pin(&mut self.blue, on),
+        }
+    }
+
+    /// Is this colour on?
+    pub fn is_on(&self, led: Led) -> bool {
+        match led {
+            Led::Red => pin_is_set(&self.red),
+            Led::Green => pin_is_set(&self.green),
+            Led::Blue => pin_is_set(&self.blue),
+        }
+    }
+
+    /// Switch one colour from on to off, or off to on.
+    pub fn toggle(&mut self, led: Led) {
+        let on = self.is_on(led);
+        self.set(led, !on);
+    }
+
+    /// Set all three colours at once.
+    pub fn set_color(&mut self, red: bool, green: bool, blue: bool) {
+        set_pin(&mut self.red, red);
+        set_pin(&mut self.green, green);
+        set_pin(&mut self.blue, blue);
+    }
+
+    /// Switch to PWM mode. The LED starts off.
+    pub fn into_pwm(
+        self,
+        timer0: TIMER0,
+        timer1: TIMER1,
+        control: &mut GpioControl,
+        power_control: &PowerControl,
+    ) -> RgbPwm {
+        sysctl::control_power(power_control, Domain::Timer0, RunMode::Run, PowerState::On);
+        sysctl::reset(power_control, Domain::Timer0);
+        sysctl::control_power(power_control, Domain::Timer1, RunMode::Run, PowerState::On);
+        sysctl::reset(power_control, Domain::Timer1);
+
+        // Red is on the B half of TIMER0, so only that half is running.
+        setup_pwm(&timer0, &[Half::B]);
+        setup_pwm(&timer1, &[Half::A, Half::B]);
+
+        RgbPwm {
+            timer0,
+            timer1,
+            red: self.red.into_af_push_pull::<AF7>(control),
+            green: self.green.into_af_push_pull::<AF7>(control),
+            blue: self.blue.into_af_push_pull::<AF7>(control),
+            calibration: DEFAULT_CALIBRATION,
+            gamma: true,
+        }
+    }
+
+    /// Give back the raw pins, as (red, green, blue).
+    pub fn into_pins(
+        self,
+    ) -> (
+        PF1<Output<PushPull>>,
+        PF3<Output<PushPull>>,
+        PF2<Output<PushPull>>,
+    ) {
+        (self.red, self.green, self.blue)
+    }
+}
+
+impl RgbPwm {
+    /// Set the brightness of each colour, from 0 (off) to 255 (fully on).
+    pub fn set_color(&mut self, red: u8, green: u8, blue: u8) {
+        self.set_rgb(Rgb::new(red, green, blue));
+    }
+
+    /// Set the colour.
+    pub fn set_rgb(&mut self, colour: Rgb) {
+        let colour = colour.calibrate(self.calibration);
+        let duty = if self.gamma {
+            colour::gamma
+        } else {
+            colour::linear
+        };
+        set_duty(&self.timer0, Half::B, duty(colour.red));
+        set_duty(&self.timer1, Half::B, duty(colour.green));
+        set_duty(&self.timer1, Half::A, duty(colour.blue));
+    }
+
+    /// Set the colour from hue (in degrees), saturation and value (0 to
+    /// 255). See `Rgb::from_hsv`.
+    pub fn set_hsv(&mut self, hue: u16, saturation: u8, value: u8) {
+        self.set_rgb(Rgb::from_hsv(hue, saturation, value));
+    }
+
+    /// Set the brightness each channel gets when asked for 255. Takes effect
+    /// from the next colour set.
+    pub fn set_calibration(&mut self, calibration: Rgb) {
+        self.calibration = calibration;
+    }
+
+    /// Turn gamma correction on (the default) or off. Takes effect from the
+    /// next colour set.
+    pub fn set_gamma(&mut self, enabled: bool) {
+        self.gamma = enabled;
+    }
+
+    /// Stop the timers and go back to switching the colours on and off.
+    /// The LED starts off. Also gives back the timers.
+    pub fn into_digital(self) -> (RgbLed, TIMER0, TIMER1) {
+        self.timer0.ctl.write(|w| unsafe { w.bits(0) });
+        self.timer1.ctl.write(|w| unsafe { w.bits(0) });
+        let led = RgbLed::new(
+            self.red.into_push_pull_output(),
+            self.green.into_push_pull_output(),
+            self.blue.into_push_pull_output(),
+        );
+        (led, self.timer0, self.timer1)
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn set_pin<P: OutputPin>(pin: &mut P, on: bool) {
+    // GPIO writes can't fail on this chip.
+    let _ = if on { pin.set_high() } else { pin.set_low() };
+}
+
+fn pin_is_set<P: StatefulOutputPin>(pin: &P) -> bool {
+    pin.is_set_high().unwrap_or(false)
+}
+
+/// Put the given halves of a timer into 16-bit PWM mode, with the output
+/// low, and start them.
+fn setup_pwm(timer: &timer0::RegisterBlock, halves: &[Half]) {
+    timer.ctl.write(|w| unsafe { w.bits(0) });
+    timer.cfg.write(|w| unsafe { w.bits(CFG_16_BIT) });
+    let mut enable = 0;
+    for half in halves {
+        match *half {
+            Half::A => {
+                timer
+                    .tamr
+                    .write(|w| unsafe { w.bits(MR_AMS | MR_PERIODIC) });
+                timer.tailr.write(|w| unsafe { w.bits(PERIOD) });
+                timer.tamatchr.write(|w| unsafe { w.bits(PERIOD) });
+                enable |= CTL_TAEN;
+            }
+            Half::B => {
+                timer
+                    .tbmr
+                    .write(|w| unsafe { w.bits(MR_AMS | MR_PERIODIC) });
+                timer.tbilr.write(|w| unsafe { w.bits(PERIOD) });
+                timer.tbmatchr.write(|w| unsafe { w.bits(PERIOD) });
+                enable |= CTL_TBEN;
+            }
+        }
+    }
+    timer.ctl.write(|w| unsafe { w.bits(enable) });
+}
+
+/// The output goes high when the counter reloads and low when it counts down
+/// to the match value, so the match value is the time spent low. `duty` runs
+/// from 0 (always low) to 65535 (always high).
+fn set_duty(timer: &timer0::RegisterBlock, half: Half, duty: u16) {
+    let high = PERIOD * u32::from(duty) / 65535;
+    let match_value = PERIOD - high;
+    match half {
+        Half::A => timer.tamatchr.write(|w| unsafe { w.bits(match_value) }),
+        Half::B => timer.tbmatchr.write(|w| unsafe { w.bits(match_value) }),
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,141 @@
+//! The Launchpad end of the telemetry protocol (see the `telemetry` crate):
+//! `Telemetry` messages out, `Command`s in, framed over any serial port.
+//!
+//! Use a `uart::BufferedUart` (with its port's `uartN` feature), so sending
+//! doesn't hold the application up and commands aren't lost between calls to
+//! `poll`. The console's UART0 works (it's the one on the USB cable), but
+//! then don't print to the console - the host would read the text as bad
+//! frames.
+//!
+//! The PC end is the `stellaris-launchpad-telemetry-host` crate. See
+//! `examples/telemetry.rs`.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use crate::telemetry::{self, Command, Encode, FrameDecoder, MAX_FRAME};
+use embedded_hal::serial;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A telemetry link over a serial port.
+pub struct Link<S> {
+    serial: S,
+    frames: FrameDecoder,
+}
+
+/// Something which went wrong sending or receiving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// A message wouldn't encode, or a received frame was bad.
+    Protocol(telemetry::Error),
+    /// The serial port failed.
+    Serial(E),
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl<S> Link<S> {
+    /// Run the protocol over `serial`.
+    pub fn new(serial: S) -> Link<S> {
+        Link {
+            serial,
+            frames: FrameDecoder::new(),
+        }
+    }
+
+    /// Get the serial port back.
+    pub fn free(self) -> S {
+        self.serial
+    }
+}
+
+impl<S> Link<S>
+where
+    S: serial::Write<u8>,
+{
+    /// Send a message (usually a `telemetry::Telemetry`), waiting for room
+    /// in the serial port if need be.
+    pub fn send<M: Encode>(&mut self, message: &M) -> Result<(), Error<S::Error>> {
+        let mut frame = [0u8; MAX_FRAME];
+        let len = telemetry::encode_frame(message, &mut frame).map_err(Error::Protocol)?;
+        for &byte in &frame[..len] {
+            nb::block!(self.serial.write(byte)).map_err(Error::Serial)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S> Link<S>
+where
+    S: serial::Read<u8>,
+{
+    /// Read whatever has arrived, returning the first command it finishes
+    /// (or a bad frame), or `None` if there isn't one yet. Call it until it
+    /// returns `None`.
+    pub fn poll(&mut self) -> Option<Result<Command, Error<S::Error>>> {
+        loop {
+            let byte = match self.serial.read() {
+                Ok(byte) => byte,
+                Err(nb::Error::WouldBlock) => return None,
+                Err(nb::Error::Other(e)) => return Some(Err(Error::Serial(e))),
+            };
+            if let Some(frame) = self.frames.push(byte) {
+                return Some(
+                    frame
+                        .and_then(telemetry::from_bytes::<Command>)
+                        .map_err(Error::Protocol),
+                );
+            }
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -6,6 +6,36 @@
 //
 // ****************************************************************************
 
+mod buttons;
+pub mod commands;
+mod config;
+pub mod console;
+pub mod crash;
+pub mod dma;
+pub mod edges;
+mod eeprom;
+pub mod effects;
+mod fault_code;
+mod fault_uart;
+mod gptm;
+mod led;
+pub mod link;
+mod parts;
+pub mod time;
+pub mod timers;
+pub mod uart;
+
+pub use self::buttons::{ButtonEvent, ButtonTiming, Buttons};
+pub use self::config::{BoardConfig, ClockSource};
+pub use self::crash::{CrashKind, CrashRecord, ResetCause};
+pub use self::eeprom::EepromError;
+pub use self::fault_code::halt_with_code;
+pub(crate) use self::fault_uart::FaultUart;
+pub use self::led::{RgbLed, RgbPwm, DEFAULT_CALIBRATION};
+pub use self::parts::{
+    Adc, ButtonPins, Console, Gpio, I2c, Leds, Other, Parts, Ssi, Storage, Timers, Uarts,
+};
+
 // ****************************************************************************
 //
 // Public Types
@@ -24,7 +54,7 @@ pub enum Led {
     Green,
 }
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 /// The Launchpad has two buttons
 pub enum Button {
     /// SW1
@@ -47,13 +77,17 @@ pub enum Button {
 //
 // ****************************************************************************
 
-use embedded_hal::digital::v2::OutputPin;
+use embedded_hal::digital::v2::{OutputPin, StatefulOutputPin};
 use tm4c123x_hal::gpio::{gpiof::*, GpioExt, Input, Output, PullUp, PushPull};
-use tm4c123x_hal::sysctl::{
-    Clocks, CrystalFrequency, Oscillator, PllOutputFrequency, SysctlExt, SystemClock,
-};
+use tm4c123x_hal::sysctl::{Clocks, SysctlExt};
 use tm4c123x_hal::time::Hertz;
 
+use crate::common::fault;
+#[cfg(all(feature = "rtic", feature = "alloc"))]
+use crate::common::heap;
+use crate::common::once::WriteOnce;
+use crate::common::stack;
+
 /// Represents the EK-LM4F120XL LaunchPad board, with the locations of the LEDs and buttons
 /// predefined.
 #[allow(non_snake_case)]
@@ -190,26 +224,99 @@ pub struct Board {
     pub FLASH_CTRL: tm4c123x_hal::tm4c123x::FLASH_CTRL,
     #[doc = "UDMA"]
     pub UDMA: tm4c123x_hal::tm4c123x::UDMA,
+
+    reset_cause: ResetCause,
+    last_crash: Option<CrashRecord>,
 }
 
-// a moderately sane default, but will be replaced by Board::new()
-static mut CLOCKS: Clocks = Clocks {
+// written once, by Board::new(), when the clock tree is frozen
+static CLOCKS: WriteOnce<Clocks> = WriteOnce::new();
+
+// what the chip runs at out of reset - the 16 MHz PIOSC
+static RESET_CLOCKS: Clocks = Clocks {
     osc: Hertz(16_000_000),
-    sysclk: Hertz(66_666_667),
+    sysclk: Hertz(16_000_000),
 };
 
-/// Get the current clock rate of the CPU
+/// Get the current clock rate of the CPU.
+///
+/// Panics if called before the clocks have been set up by `Board::new()`.
 pub fn clocks() -> &'static Clocks {
-    unsafe { &CLOCKS }
+    match CLOCKS.get() {
+        Some(clocks) => clocks,
+        None => panic!("board::clocks() called before the clocks were set up"),
+    }
+}
+
+/// Get the current clock rate of the CPU, or `None` if the clocks haven't
+/// been set up yet.
+pub fn try_clocks() -> Option<&'static Clocks> {
+    CLOCKS.get()
+}
+
+/// The clocks for use in panic and fault handlers, which mustn't themselves
+/// panic. Falls back to the reset clock if `Board::new()` hasn't run.
+pub(crate) fn fault_clocks() -> &'static Clocks {
+    CLOCKS.get().unwrap_or(&RESET_CLOCKS)
 }
 
 impl Board {
-    /// Initialise everything on the board - FPU, PLL, SysTick, GPIO and the LEDs
-    /// and buttons. Should be pretty much the first call you make in `main()`.
-    /// Doesn't init the UART - that's separate.
-    pub(crate) fn new() -> Board {
+    /// Initialise everything on the board - FPU, clock tree (as described by
+    /// `config`), SysTick, GPIO and the LEDs and buttons. Should be pretty
+    /// much the first call you make in `main()`. Doesn't init the UART -
+    /// that's separate.
+    #[cfg(not(feature = "rtic"))]
+    pub(crate) fn new(config: BoardConfig) -> Board {
         let core_peripherals = tm4c123x_hal::CorePeripherals::take().unwrap();
         let peripherals = tm4c123x_hal::Peripherals::take().unwrap();
+        Board::init(core_peripherals, peripherals, config)
+    }
+
+    /// Initialise the board from peripherals something else has already
+    /// taken - RTIC's `init`, say - with the default `BoardConfig`. Only
+    /// with the `rtic` feature, which leaves the entry point and interrupt
+    /// handlers to the application.
+    ///
+    /// Does what the crate's own start-up would have done before `main`:
+    /// paints the stack, sets up the heap (with the `alloc` feature) and
+    /// then everything `Board::new` does. Call it once, with interrupts
+    /// still disabled.
+    #[cfg(feature = "rtic")]
+    pub fn from_peripherals(
+        core_peripherals: tm4c123x_hal::CorePeripherals,
+        peripherals: tm4c123x_hal::Peripherals,
+    ) -> Board {
+        Board::from_peripherals_with_config(core_peripherals, peripherals, BoardConfig::default())
+    }
+
+    /// As `from_peripherals`, but with the given `BoardConfig`.
+    #[cfg(feature = "rtic")]
+    pub fn from_peripherals_with_config(
+        core_peripherals: tm4c123x_hal::CorePeripherals,
+        peripherals: tm4c123x_hal::Peripherals,
+        config: BoardConfig,
+    ) -> Board {
+        unsafe {
+            stack::paint();
+            #[cfg(feature = "alloc")]
+            heap::init();
+        }
+        Board::init(core_peripherals, peripherals, config)
+    }
+
+    fn init(
+        core_peripherals: tm4c123x_hal::CorePeripherals,
+        peripherals: tm4c123x_hal::Peripherals,
+        config: BoardConfig,
+    ) -> Board {
+        // find out why we reset, then clear it ready for next time
+        let reset_cause = crash::reset_cause(peripherals.SYSCTL.resc.read().bits());
+        peripherals.SYSCTL.resc.write(|w| unsafe { w.bits(0) });
+        let last_crash = crash::take_last_crash(reset_cause);
+
+        let mirror_crash = config.mirrors_crash();
+        let (trap_divide_by_zero, trap_unaligned) = config.traps();
+        let stack_guard = config.guards_stack();
         let mut sysctl = peripherals.SYSCTL.constrain();
 
         // this might belong in tm4c123x_hal, but allow FPU usage
@@ -220,13 +327,27 @@ impl Board {
             });
         }
 
-        sysctl.clock_setup.oscillator = Oscillator::Main(
-            CrystalFrequency::_16mhz,
-            SystemClock::UsePll(PllOutputFrequency::_80_00mhz),
-        );
-        unsafe {
-            CLOCKS = sysctl.clock_setup.freeze();
+        // give MemManage, BusFault and UsageFault their own handlers, so
+        // they get decoded properly
+        fault::enable_traps(&core_peripherals.SCB, trap_divide_by_zero, trap_unaligned);
+        if stack_guard {
+            stack::enable_guard(&core_peripherals.MPU);
         }
+
+        sysctl.clock_setup.oscillator = config.oscillator();
+        if CLOCKS.set(sysctl.clock_setup.freeze()).is_err() {
+            panic!("clocks frozen twice");
+        }
+
+        if mirror_crash {
+            if let Some(ref record) = last_crash {
+                // Nothing sensible to do if this fails - we still have the
+                // copy in RAM.
+                let _ = eeprom::init(&peripherals.EEPROM, &sysctl.power_control)
+                    .and_then(|_| record.store(&peripherals.EEPROM));
+            }
+        }
+
         let mut pins = peripherals.GPIO_PORTF.split(&sysctl.power_control);
         let led_red = pins.pf1.into_push_pull_output();
         let led_blue = pins.pf2.into_push_pull_output();
@@ -299,7 +420,63 @@ impl Board {
             HIB: peripherals.HIB,
             FLASH_CTRL: peripherals.FLASH_CTRL,
             UDMA: peripherals.UDMA,
+            reset_cause,
+            last_crash,
+        }
+    }
+
+    /// Switch one of the LEDs on or off. For all three at once, or PWM, move
+    /// the pins into an `RgbLed`.
+    pub fn set_led(&mut self, led: Led, on: bool) {
+        // GPIO writes can't fail on this chip.
+        let _ = match (led, on) {
+            (Led::Red, true) => self.led_red.set_high(),
+            (Led::Red, false) => self.led_red.set_low(),
+            (Led::Blue, true) => self.led_blue.set_high(),
+            (Led::Blue, false) => self.led_blue.set_low(),
+            (Led::Green, true) => self.led_green.set_high(),
+            (Led::Green, false) => self.led_green.set_low(),
+        };
+    }
+
+    /// Is one of the LEDs on?
+    pub fn is_led_on(&self, led: Led) -> bool {
+        match led {
+            Led::Red => self.led_red.is_set_high(),
+            Led::Blue => self.led_blue.is_set_high(),
+            Led::Green => self.led_green.is_set_high(),
         }
+        .unwrap_or(false)
+    }
+
+    /// Switch one of the LEDs from on to off, or off to on.
+    pub fn toggle_led(&mut self, led: Led) {
+        let on = self.is_led_on(led);
+        self.set_led(led, !on);
+    }
+
+    /// Why the chip last came out of reset.
+    pub fn reset_cause(&self) -> ResetCause {
+        self.reset_cause
+    }
+
+    /// The panic or fault which caused the last reset, if there was one and
+    /// the RAM it was recorded in survived.
+    pub fn last_crash(&self) -> Option<&CrashRecord> {
+        self.last_crash.as_ref()
+    }
+
+    /// The crash record mirrored into the EEPROM (see
+    /// `BoardConfig::mirror_crash_to_eeprom`), which survives a power cycle.
+    pub fn stored_crash(&self) -> Result<Option<CrashRecord>, EepromError> {
+        eeprom::init(&self.EEPROM, &self.power_control)?;
+        CrashRecord::load(&self.EEPROM)
+    }
+
+    /// Erase the crash record mirrored into the EEPROM.
+    pub fn clear_stored_crash(&self) -> Result<(), EepromError> {
+        eeprom::init(&self.EEPROM, &self.power_control)?;
+        CrashRecord::erase(&self.EEPROM)
     }
 }
 
@@ -309,21 +486,17 @@ impl Board {
 //
 // ****************************************************************************
 
-/// Call from a panic handler to flash the red LED quickly.
+/// Call from a panic handler to blink the LED code for whatever failure was
+/// recorded this boot (a plain panic, if nothing was). See
+/// `halt_with_code` for the codes.
 pub fn panic() -> ! {
-    use embedded_hal::blocking::delay::DelayMs;
-    let core_peripherals = unsafe { tm4c123x_hal::CorePeripherals::steal() };
-    let p = unsafe { tm4c123x_hal::Peripherals::steal() };
-    let pins = p.GPIO_PORTF.split(&p.SYSCTL.constrain().power_control);
-
-    let mut delay = tm4c123x_hal::delay::Delay::new(core_peripherals.SYST, unsafe { &CLOCKS });
-    let mut led_red = pins.pf1.into_push_pull_output();
-    loop {
-        let _ = led_red.set_high();
-        delay.delay_ms(200u32);
-        let _ = led_red.set_low();
-        delay.delay_ms(200u32);
-    }
+    let (kind, detail) = crash::current().unwrap_or((CrashKind::Panic, 0));
+    halt_with_code(kind, detail)
+}
+
+/// Get UART0 ready for a panic or fault handler to report what happened.
+pub(crate) fn fault_uart() -> FaultUart {
+    FaultUart::new()
 }
 
 // ****************************************************************************
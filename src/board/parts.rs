@@ -0,0 +1,378 @@
+//! `Board::split`, which breaks the `Board` up into groups of related
+//! resources. Each group is a plain struct which can be moved on its own -
+//! the LEDs to one task, the SSI ports to the LCD driver, the timers to
+//! whatever needs them - without partially moving the whole `Board`.
+//!
+//! ```ignore
+//! let parts = board.split();
+//! let mut delay = Delay::new(parts.core_peripherals.SYST, board::clocks());
+//! let mut led = parts.leds.into_rgb_led();
+//! let mut buttons = parts.buttons.into_buttons(ButtonTiming::default());
+//! time::init(parts.timers.wtimer5, &parts.power_control);
+//! timers::start(parts.timers.timer3, &parts.power_control);
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use tm4c123x_hal::gpio::gpiof::{GpioControl, PF0, PF1, PF2, PF3, PF4};
+use tm4c123x_hal::gpio::{Input, Output, PullUp, PushPull};
+use tm4c123x_hal::sysctl::PowerControl;
+use tm4c123x_hal::tm4c123x::{
+    ADC0, ADC1, CAN0, CAN1, COMP, EEPROM, FLASH_CTRL, GPIO_PORTA, GPIO_PORTA_AHB, GPIO_PORTB,
+    GPIO_PORTB_AHB, GPIO_PORTC, GPIO_PORTC_AHB, GPIO_PORTD, GPIO_PORTD_AHB, GPIO_PORTE,
+    GPIO_PORTE_AHB, GPIO_PORTF_AHB, HIB, I2C0, I2C1, I2C2, I2C3, PWM0, PWM1, QEI0, QEI1, SSI0,
+    SSI1, SSI2, SSI3, SYSEXC, TIMER0, TIMER1, TIMER2, TIMER3, TIMER4, TIMER5, UART0, UART1, UART2,
+    UART3, UART4, UART5, UART6, UART7, UDMA, USB0, WATCHDOG0, WATCHDOG1, WTIMER0, WTIMER1, WTIMER2,
+    WTIMER3, WTIMER4, WTIMER5,
+};
+use tm4c123x_hal::CorePeripherals;
+
+use super::console::PortA;
+use super::{
+    console, eeprom, Board, ButtonTiming, Buttons, CrashRecord, EepromError, ResetCause, RgbLed,
+};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// The `Board`, in groups. See `Board::split`.
+pub struct Parts {
+    /// The core peripherals on the LM4F120 / TM4C1233
+    pub core_peripherals: CorePeripherals,
+    /// Power gating for the peripherals, which their drivers borrow.
+    pub power_control: PowerControl,
+    /// The tri-colour LED
+    pub leds: Leds,
+    /// The two switches
+    pub buttons: ButtonPins,
+    /// The UART wired to the debug USB port
+    pub console: Console,
+    /// The other UARTs
+    pub uarts: Uarts,
+    /// The general-purpose timers
+    pub timers: Timers,
+    /// The SSI (SPI) ports
+    pub ssi: Ssi,
+    /// The I2C ports
+    pub i2c: I2c,
+    /// The analogue peripherals
+    pub adc: Adc,
+    /// The EEPROM and flash controller
+    pub storage: Storage,
+    /// The GPIO ports
+    pub gpio: Gpio,
+    /// Everything else
+    pub other: Other,
+    /// Why the chip last came out of reset
+    pub reset_cause: ResetCause,
+    /// The panic or fault which caused the last reset, if it was recorded
+    pub last_crash: Option<CrashRecord>,
+}
+
+/// The pins of the tri-colour LED.
+pub struct Leds {
+    /// The pin used for the Red LED
+    pub red: PF1<Output<PushPull>>,
+    /// The pin used for the Blue LED
+    pub blue: PF2<Output<PushPull>>,
+    /// The pin used for the Green LED
+    pub green: PF3<Output<PushPull>>,
+    /// GPIO control for GPIO port F, for changing the pins' mode (to PWM,
+    /// say)
+    pub control: GpioControl,
+}
+
+/// The pins of the two switches.
+pub struct ButtonPins {
+    /// The pin used for Button One
+    pub one: PF4<Input<PullUp>>,
+    /// The pin used for Button Two
+    pub two: PF0<Input<PullUp>>,
+}
+
+/// UART0, which reaches the PC through the debug USB port on PA0/PA1, and
+/// port A with it. Start the console, which hands back the rest of port A,
+/// or use them yourself - though the panic and fault handlers take PA0/PA1
+/// over whatever they are doing.
+pub struct Console {
+    /// UART0
+    pub uart: UART0,
+    /// GPIO port A
+    pub porta: GPIO_PORTA,
+}
+
+/// UART1 to UART7.
+#[allow(missing_docs)]
+pub struct Uarts {
+    pub uart1: UART1,
+    pub uart2: UART2,
+    pub uart3: UART3,
+    pub uart4: UART4,
+    pub uart5: UART5,
+    pub uart6: UART6,
+    pub uart7: UART7,
+}
+
+/// The 16/32-bit and 32/64-bit timers. Hand WTIMER5 to `time::init` to run
+/// the monotonic clock.
+#[allow(missing_docs)]
+pub struct Timers {
+    pub timer0: TIMER0,
+    pub timer1: TIMER1,
+    pub timer2: TIMER2,
+    pub timer3: TIMER3,
+    pub timer4: TIMER4,
+    pub timer5: TIMER5,
+    pub wtimer0: WTIMER0,
+    pub wtimer1: WTIMER1,
+    pub wtimer2: WTIMER2,
+    pub wtimer3: WTIMER3,
+    pub wtimer4: WTIMER4,
+    pub wtimer5: WTIMER5,
+}
+
+/// The four SSI ports.
+#[allow(missing_docs)]
+pub struct Ssi {
+    pub ssi0: SSI0,
+    pub ssi1: SSI1,
+    pub ssi2: SSI2,
+    pub ssi3: SSI3,
+}
+
+/// The four I2C ports.
+#[allow(missing_docs)]
+pub struct I2c {
+    pub i2c0: I2C0,
+    pub i2c1: I2C1,
+    pub i2c2: I2C2,
+    pub i2c3: I2C3,
+}
+
+/// The two ADCs and the analogue comparators.
+#[allow(missing_docs)]
+pub struct Adc {
+    pub adc0: ADC0,
+    pub adc1: ADC1,
+    pub comp: COMP,
+}
+
+/// Non-volatile storage.
+#[allow(missing_docs)]
+pub struct Storage {
+    pub eeprom: EEPROM,
+    pub flash_ctrl: FLASH_CTRL,
+}
+
+/// GPIO ports B to E, on the APB bus, and A to F on the AHB bus. Port A is
+/// in `console`, and port F's pins are in `leds` and `buttons`.
+#[allow(missing_docs)]
+pub struct Gpio {
+    pub portb: GPIO_PORTB,
+    pub portc: GPIO_PORTC,
+    pub portd: GPIO_PORTD,
+    pub porte: GPIO_PORTE,
+    pub porta_ahb: GPIO_PORTA_AHB,
+    pub portb_ahb: GPIO_PORTB_AHB,
+    pub portc_ahb: GPIO_PORTC_AHB,
+    pub portd_ahb: GPIO_PORTD_AHB,
+    pub porte_ahb: GPIO_PORTE_AHB,
+    pub portf_ahb: GPIO_PORTF_AHB,
+}
+
+/// The peripherals that don't fit in any other group.
+#[allow(missing_docs)]
+pub struct Other {
+    pub watchdog0: WATCHDOG0,
+    pub watchdog1: WATCHDOG1,
+    pub pwm0: PWM0,
+    pub pwm1: PWM1,
+    pub qei0: QEI0,
+    pub qei1: QEI1,
+    pub can0: CAN0,
+    pub can1: CAN1,
+    pub usb0: USB0,
+    pub sysexc: SYSEXC,
+    pub hib: HIB,
+    pub udma: UDMA,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl Board {
+    /// Break the board up into groups of resources which can be moved
+    /// independently.
+    pub fn split(self) -> Parts {
+        Parts {
+            core_peripherals: self.core_peripherals,
+            power_control: self.power_control,
+            leds: Leds {
+                red: self.led_red,
+                blue: self.led_blue,
+                green: self.led_green,
+                control: self.portf_control,
+            },
+            buttons: ButtonPins {
+                one: self.button_one,
+                two: self.button_two,
+            },
+            console: Console {
+                uart: self.UART0,
+                porta: self.GPIO_PORTA,
+            },
+            uarts: Uarts {
+                uart1: self.UART1,
+                uart2: self.UART2,
+                uart3: self.UART3,
+                uart4: self.UART4,
+                uart5: self.UART5,
+                uart6: self.UART6,
+                uart7: self.UART7,
+            },
+            timers: Timers {
+                timer0: self.TIMER0,
+                timer1: self.TIMER1,
+                timer2: self.TIMER2,
+                timer3: self.TIMER3,
+                timer4: self.TIMER4,
+                timer5: self.TIMER5,
+                wtimer0: self.WTIMER0,
+                wtimer1: self.WTIMER1,
+                wtimer2: self.WTIMER2,
+                wtimer3: self.WTIMER3,
+                wtimer4: self.WTIMER4,
+                wtimer5: self.WTIMER5,
+            },
+            ssi: Ssi {
+                ssi0: self.SSI0,
+                ssi1: self.SSI1,
+                ssi2: self.SSI2,
+                ssi3: self.SSI3,
+            },
+            i2c: I2c {
+                i2c0: self.I2C0,
+                i2c1: self.I2C1,
+                i2c2: self.I2C2,
+                i2c3: self.I2C3,
+            },
+            adc: Adc {
+                adc0: self.ADC0,
+                adc1: self.ADC1,
+                comp: self.COMP,
+            },
+            storage: Storage {
+                eeprom: self.EEPROM,
+                flash_ctrl: self.FLASH_CTRL,
+            },
+            gpio: Gpio {
+                portb: self.GPIO_PORTB,
+                portc: self.GPIO_PORTC,
+                portd: self.GPIO_PORTD,
+                porte: self.GPIO_PORTE,
+                porta_ahb: self.GPIO_PORTA_AHB,
+                portb_ahb: self.GPIO_PORTB_AHB,
+                portc_ahb: self.GPIO_PORTC_AHB,
+                portd_ahb: self.GPIO_PORTD_AHB,
+                porte_ahb: self.GPIO_PORTE_AHB,
+                portf_ahb: self.GPIO_PORTF_AHB,
+            },
+            other: Other {
+                watchdog0: self.WATCHDOG0,
+                watchdog1: self.WATCHDOG1,
+                pwm0: self.PWM0,
+                pwm1: self.PWM1,
+                qei0: self.QEI0,
+                qei1: self.QEI1,
+                can0: self.CAN0,
+                can1: self.CAN1,
+                usb0: self.USB0,
+                sysexc: self.SYSEXC,
+                hib: self.HIB,
+                udma: self.UDMA,
+            },
+            reset_cause: self.reset_cause,
+            last_crash: self.last_crash,
+        }
+    }
+}
+
+impl Leds {
+    /// Drive all three together. `control` stays behind.
+    pub fn into_rgb_led(self) -> RgbLed {
+        RgbLed::new(self.red, self.green, self.blue)
+    }
+}
+
+impl ButtonPins {
+    /// Debounce the switches.
+    pub fn into_buttons(self, timing: ButtonTiming) -> Buttons {
+        Buttons::new(self.one, self.two, timing)
+    }
+}
+
+impl Console {
+    /// Start the console (see `board::console`), getting the rest of port A
+    /// back.
+    pub fn init(self, power_control: &PowerControl) -> PortA {
+        console::init(self.uart, self.porta, power_control)
+    }
+}
+
+impl Storage {
+    /// The crash record mirrored into the EEPROM. See
+    /// `Board::stored_crash`.
+    pub fn stored_crash(
+        &self,
+        power_control: &PowerControl,
+    ) -> Result<Option<CrashRecord>, EepromError> {
+        eeprom::init(&self.eeprom, power_control)?;
+        CrashRecord::load(&self.eeprom)
+    }
+
+    /// Erase the crash record mirrored into the EEPROM.
+    pub fn clear_stored_crash(&self, power_control: &PowerControl) -> Result<(), EepromError> {
+        eeprom::init(&self.eeprom, power_control)?;
+        CrashRecord::erase(&self.eeprom)
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,315 @@
+//! A monotonic clock, counting system clock ticks since `time::init`.
+//!
+//! `init` takes WTIMER5 from the `Board` and runs it as a 64-bit up-counter
+//! at the system clock rate, which takes thousands of years to wrap. Until
+//! then WTIMER5 is the application's to use however it likes, and `now()`
+//! panics. SYST stays free for `cpu::delay::Delay`, although `time::Delay`
+//! does the same job without it.
+//!
+//! ```ignore
+//! time::init(board.WTIMER5, &board.power_control);
+//! let timeout = Timeout::after(Duration::from_millis(500));
+//! while !uart_has_data() {
+//!     if timeout.expired() {
+//!         return Err(Error::Timeout);
+//!     }
+//! }
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::ops::{Add, Sub};
+use core::sync::atomic::{AtomicBool, Ordering};
+pub use core::time::Duration;
+
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use nb;
+use tm4c123x_hal::sysctl::{self, Domain, PowerControl, PowerState, RunMode};
+use tm4c123x_hal::tm4c123x::{wtimer0, WTIMER5};
+
+use super::clocks;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A moment in time, as measured by the monotonic clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant {
+    ticks: u64,
+}
+
+/// A deadline, for polling something without blocking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeout {
+    deadline: Instant,
+}
+
+/// A blocking delay which spins on the monotonic clock. Unlike
+/// `cpu::delay::Delay` it doesn't need SYST, so you can have as many as you
+/// like.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Delay;
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+// GPTMTAMR
+const TAMR_PERIODIC: u32 = 0x2;
+const TAMR_TACDIR_UP: u32 = 1 << 4;
+
+// GPTMCTL
+const CTL_TAEN: u32 = 1 << 0;
+const CTL_TASTALL: u32 = 1 << 1;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+// set once `init` has started WTIMER5
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Start the clock, which keeps `timer` for good. Call it once, after
+/// `Board::new` has set the clocks.
+pub fn init(timer: WTIMER5, power_control: &PowerControl) {
+    sysctl::control_power(
+        power_control,
+        Domain::WideTimer5,
+        RunMode::Run,
+        PowerState::On,
+    );
+    sysctl::reset(power_control, Domain::WideTimer5);
+    timer.ctl.write(|w| unsafe { w.bits(0) });
+    // 0 means 64-bit, concatenated, on a wide timer.
+    timer.cfg.write(|w| unsafe { w.bits(0) });
+    timer
+        .tamr
+        .write(|w| unsafe { w.bits(TAMR_PERIODIC | TAMR_TACDIR_UP) });
+    timer.tailr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+    timer.tbilr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+    // Stall with the CPU when a debugger halts it.
+    timer
+        .ctl
+        .write(|w| unsafe { w.bits(CTL_TAEN | CTL_TASTALL) });
+    RUNNING.store(true, Ordering::Release);
+}
+
+/// Has `init` started the clock?
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::Acquire)
+}
+
+/// The time now.
+///
+/// Panics if called before `init`.
+pub fn now() -> Instant {
+    if !is_running() {
+        panic!("time::now() called before time::init()");
+    }
+    let timer = timer();
+    // The two halves can't be read atomically, so re-read if the top half
+    // ticked over while we were reading the bottom half.
+    loop {
+        let high = timer.tbv.read().bits();
+        let low = timer.tav.read().bits();
+        if timer.tbv.read().bits() == high {
+            return Instant {
+                ticks: (u64::from(high) << 32) | u64::from(low),
+            };
+        }
+    }
+}
+
+impl Instant {
+    /// The time since `init` started the clock.
+    pub fn since_boot(self) -> Duration {
+        ticks_to_duration(self.ticks)
+    }
+
+    /// The time between `earlier` and this instant, or zero if `earlier`
+    /// is actually later.
+    pub fn duration_since(self, earlier: Instant) -> Duration {
+        ticks_to_duration(self.ticks.saturating_sub(earlier.ticks))
+    }
+
+    /// The time since this instant.
+    pub fn elapsed(self) -> Duration {
+        now().duration_since(self)
+    }
+
+    /// The raw count of system clock ticks.
+    pub fn ticks(self) -> u64 {
+        self.ticks
+    }
+
+    /// The instant a raw count of system clock ticks after boot.
+    pub(crate) fn from_ticks(ticks: u64) -> Instant {
+        Instant { ticks }
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, rhs: Duration) -> Instant {
+        Instant {
+            ticks: self.ticks.saturating_add(duration_to_ticks(rhs)),
+        }
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, rhs: Duration) -> Instant {
+        Instant {
+            ticks: self.ticks.saturating_sub(duration_to_ticks(rhs)),
+        }
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, rhs: Instant) -> Duration {
+        self.duration_since(rhs)
+    }
+}
+
+impl Timeout {
+    /// A timeout which expires `duration` from now.
+    pub fn after(duration: Duration) -> Timeout {
+        Timeout::at(now() + duration)
+    }
+
+    /// A timeout which expires at `deadline`.
+    pub fn at(deadline: Instant) -> Timeout {
+        Timeout { deadline }
+    }
+
+    /// When it expires.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Has it expired?
+    pub fn expired(&self) -> bool {
+        now() >= self.deadline
+    }
+
+    /// How long until it expires (zero if it has).
+    pub fn remaining(&self) -> Duration {
+        self.deadline.duration_since(now())
+    }
+
+    /// A non-blocking wait, for use with `nb::block!` or in a polling loop.
+    pub fn wait(&self) -> nb::Result<(), core::convert::Infallible> {
+        if self.expired() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl DelayMs<u32> for Delay {
+    fn delay_ms(&mut self, ms: u32) {
+        spin(Duration::from_millis(u64::from(ms)));
+    }
+}
+
+impl DelayMs<u16> for Delay {
+    fn delay_ms(&mut self, ms: u16) {
+        self.delay_ms(u32::from(ms));
+    }
+}
+
+impl DelayMs<u8> for Delay {
+    fn delay_ms(&mut self, ms: u8) {
+        self.delay_ms(u32::from(ms));
+    }
+}
+
+impl DelayUs<u32> for Delay {
+    fn delay_us(&mut self, us: u32) {
+        spin(Duration::from_micros(u64::from(us)));
+    }
+}
+
+impl DelayUs<u16> for Delay {
+    fn delay_us(&mut self, us: u16) {
+        self.delay_us(u32::from(us));
+    }
+}
+
+impl DelayUs<u8> for Delay {
+    fn delay_us(&mut self, us: u8) {
+        self.delay_us(u32::from(us));
+    }
+}
+
+/// Convert a duration to system clock ticks.
+pub(crate) fn duration_to_ticks(duration: Duration) -> u64 {
+    let hz = u64::from(clocks().sysclk.0);
+    duration
+        .as_secs()
+        .saturating_mul(hz)
+        .saturating_add(u64::from(duration.subsec_nanos()) * hz / NANOS_PER_SEC)
+}
+
+/// Convert system clock ticks to a duration.
+pub(crate) fn ticks_to_duration(ticks: u64) -> Duration {
+    let hz = u64::from(clocks().sysclk.0);
+    let nanos = (ticks % hz) * NANOS_PER_SEC / hz;
+    Duration::new(ticks / hz, nanos as u32)
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn spin(duration: Duration) {
+    let timeout = Timeout::after(duration);
+    while !timeout.expired() {}
+}
+
+fn timer() -> &'static wtimer0::RegisterBlock {
+    unsafe { &*WTIMER5::ptr() }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,417 @@
+//! Software timers: many one-shot and periodic callbacks, sharing TIMER3.
+//!
+//! Deadlines are kept against the monotonic clock (`board::time`), and
+//! TIMER3A is set as a one-shot for whichever comes first. Callbacks run in
+//! the TIMER3A interrupt, in deadline order, so keep them short - set a flag
+//! or start an LED effect, don't redraw the LCD.
+//!
+//! If a periodic timer's callback runs so late that one or more whole periods
+//! have gone by, those periods are skipped (the timer stays in phase) and
+//! counted as missed deadlines - see `missed_deadlines` and `stats`.
+//!
+//! For `async` code, `sleep` and `sleep_until` are futures which use a timer
+//! each to wake their task (see `common::executor`).
+//!
+//! The crate only binds TIMER3A with the `timers` feature. Without it, the
+//! application's TIMER3A handler must call `on_interrupt`.
+//!
+//! ```ignore
+//! time::init(board.WTIMER5, &board.power_control);
+//! timers::start(board.TIMER3, &board.power_control);
+//! let scan = timers::schedule_periodic(Duration::from_millis(20), scan_keypad)?;
+//! timers::schedule_once(Duration::from_secs(5), |_| effects::play(Effect::Off))?;
+//! ...
+//! timers::cancel(scan);
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use cortex_m::interrupt::{self, Mutex};
+use cortex_m::peripheral::NVIC;
+use tm4c123x_hal::sysctl::{self, Domain, PowerControl, PowerState, RunMode};
+use tm4c123x_hal::tm4c123x::{Interrupt, TIMER3};
+
+use super::gptm;
+use super::time::{self, Duration, Instant};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Identifies a scheduled timer. Stays unique after the timer has finished
+/// or been cancelled, so a stale id can't cancel someone else's timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId {
+    slot: u8,
+    generation: u16,
+}
+
+/// Why a timer couldn't be scheduled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerError {
+    /// `start` hasn't been called.
+    NotStarted,
+    /// All `MAX_TIMERS` slots are in use.
+    Full,
+}
+
+/// How the timer service is coping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerStats {
+    /// The number of timers scheduled right now.
+    pub active: usize,
+    /// The total number of periods skipped by periodic timers.
+    pub missed_deadlines: u32,
+    /// The latest any callback has run, relative to its deadline.
+    pub max_lateness: Duration,
+}
+
+/// A future which completes at a deadline. See `sleep`.
+#[derive(Debug)]
+pub struct Sleep {
+    deadline: Instant,
+    timer: Option<TimerId>,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// How many timers can be scheduled at once.
+pub const MAX_TIMERS: usize = 16;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+#[derive(Clone, Copy)]
+struct Entry {
+    deadline: Instant,
+    /// In clock ticks; zero for a one-shot.
+    period: u64,
+    callback: fn(TimerId),
+    missed: u32,
+}
+
+struct Service {
+    timer: TIMER3,
+    slots: [Option<Entry>; MAX_TIMERS],
+    generations: [u16; MAX_TIMERS],
+    missed_deadlines: u32,
+    max_lateness: u64,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+static SERVICE: Mutex<RefCell<Option<Service>>> = Mutex::new(RefCell::new(None));
+
+/// The tasks waiting on a `Sleep`, by the slot of its timer.
+static WAKERS: Mutex<RefCell<[Option<Waker>; MAX_TIMERS]>> =
+    Mutex::new(RefCell::new([NO_WAKER; MAX_TIMERS]));
+
+const NO_WAKER: Option<Waker> = None;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Take over TIMER3 and start the service. Call after `time::init`.
+pub fn start(timer: TIMER3, power_control: &PowerControl) {
+    if !time::is_running() {
+        panic!("timers::start() called before time::init()");
+    }
+    sysctl::control_power(power_control, Domain::Timer3, RunMode::Run, PowerState::On);
+    sysctl::reset(power_control, Domain::Timer3);
+    interrupt::free(|cs| {
+        *SERVICE.borrow(cs).borrow_mut() = Some(Service {
+            timer,
+            slots: [None; MAX_TIMERS],
+            generations: [0; MAX_TIMERS],
+            missed_deadlines: 0,
+            max_lateness: 0,
+        });
+    });
+    unsafe { NVIC::unmask(Interrupt::TIMER3A) };
+}
+
+/// Stop the service, dropping every timer, and hand back TIMER3.
+pub fn stop() -> Option<TIMER3> {
+    NVIC::mask(Interrupt::TIMER3A);
+    interrupt::free(|cs| {
+        SERVICE.borrow(cs).borrow_mut().take().map(|service| {
+            gptm::stop(&service.timer);
+            service.timer
+        })
+    })
+}
+
+/// Call `callback` once, `delay` from now.
+pub fn schedule_once(delay: Duration, callback: fn(TimerId)) -> Result<TimerId, TimerError> {
+    schedule(time::now() + delay, 0, callback)
+}
+
+/// Call `callback` once, at `deadline`. If that's already passed, it's
+/// called straight away (from the interrupt).
+pub fn schedule_at(deadline: Instant, callback: fn(TimerId)) -> Result<TimerId, TimerError> {
+    schedule(deadline, 0, callback)
+}
+
+/// Call `callback` every `period`, starting `period` from now, until
+/// cancelled.
+pub fn schedule_periodic(period: Duration, callback: fn(TimerId)) -> Result<TimerId, TimerError> {
+    let ticks = time::duration_to_ticks(period).max(1);
+    schedule(time::now() + period, ticks, callback)
+}
+
+/// Cancel a timer. Returns `false` if it had already finished or been
+/// cancelled.
+pub fn cancel(id: TimerId) -> bool {
+    interrupt::free(|cs| match *SERVICE.borrow(cs).borrow_mut() {
+        Some(ref mut service) if service.is_current(id) => {
+            service.slots[usize::from(id.slot)] = None;
+            service.arm();
+            true
+        }
+        _ => false,
+    })
+}
+
+/// Is this timer still scheduled?
+pub fn is_active(id: TimerId) -> bool {
+    interrupt::free(|cs| {
+        SERVICE
+            .borrow(cs)
+            .borrow()
+            .as_ref()
+            .map_or(false, |service| service.is_current(id))
+    })
+}
+
+/// How many periods this (periodic) timer has skipped because its
+/// callback ran too late, or `None` if it's no longer scheduled.
+pub fn missed_deadlines(id: TimerId) -> Option<u32> {
+    interrupt::free(|cs| {
+        SERVICE.borrow(cs).borrow().as_ref().and_then(|service| {
+            if service.is_current(id) {
+                service.slots[usize::from(id.slot)].map(|entry| entry.missed)
+            } else {
+                None
+            }
+        })
+    })
+}
+
+/// Statistics for the whole service, or `None` if it isn't running.
+pub fn stats() -> Option<TimerStats> {
+    interrupt::free(|cs| {
+        SERVICE
+            .borrow(cs)
+            .borrow()
+            .as_ref()
+            .map(|service| TimerStats {
+                active: service.slots.iter().filter(|s| s.is_some()).count(),
+                missed_deadlines: service.missed_deadlines,
+                max_lateness: time::ticks_to_duration(service.max_lateness),
+            })
+    })
+}
+
+/// A future which completes `duration` from now.
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(time::now() + duration)
+}
+
+/// A future which completes at `deadline`. It needs the service to be
+/// started - without it, the task is polled again and again until the
+/// deadline.
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep {
+        deadline,
+        timer: None,
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if time::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        let deadline = self.deadline;
+        let current = self.timer;
+        // The timer can't fire until we leave the critical section, by which
+        // time the waker is in place.
+        let timer = interrupt::free(|cs| {
+            let timer = match current {
+                Some(id) if is_active(id) => Some(id),
+                _ => schedule_at(deadline, wake_sleeper).ok(),
+            };
+            if let Some(id) = timer {
+                WAKERS.borrow(cs).borrow_mut()[usize::from(id.slot)] = Some(cx.waker().clone());
+            }
+            timer
+        });
+        if timer.is_none() {
+            cx.waker().wake_by_ref();
+        }
+        self.timer = timer;
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(id) = self.timer {
+            if cancel(id) {
+                interrupt::free(|cs| WAKERS.borrow(cs).borrow_mut()[usize::from(id.slot)] = None);
+            }
+        }
+    }
+}
+
+/// Run whatever's due, then wait for the next deadline. Called from the
+/// crate's TIMER3A interrupt handler with the `timers` feature - otherwise
+/// from the application's.
+pub fn on_interrupt() {
+    let mut due: [Option<(fn(TimerId), TimerId)>; MAX_TIMERS] = [None; MAX_TIMERS];
+    interrupt::free(|cs| {
+        if let Some(ref mut service) = *SERVICE.borrow(cs).borrow_mut() {
+            gptm::clear_timeout(&service.timer);
+            service.collect_due(time::now(), &mut due);
+            service.arm();
+        }
+    });
+    // Outside the critical section, so callbacks can schedule and cancel
+    // timers, and other interrupts aren't held up.
+    for &(callback, id) in due.iter().flatten() {
+        callback(id);
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn wake_sleeper(id: TimerId) {
+    let waker = interrupt::free(|cs| WAKERS.borrow(cs).borrow_mut()[usize::from(id.slot)].take());
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+fn schedule(deadline: Instant, period: u64, callback: fn(TimerId)) -> Result<TimerId, TimerError> {
+    interrupt::free(|cs| {
+        let mut service = SERVICE.borrow(cs).borrow_mut();
+        let service = service.as_mut().ok_or(TimerError::NotStarted)?;
+        let slot = service
+            .slots
+            .iter()
+            .position(|s| s.is_none())
+            .ok_or(TimerError::Full)?;
+        service.generations[slot] = service.generations[slot].wrapping_add(1);
+        service.slots[slot] = Some(Entry {
+            deadline,
+            period,
+            callback,
+            missed: 0,
+        });
+        service.arm();
+        Ok(TimerId {
+            slot: slot as u8,
+            generation: service.generations[slot],
+        })
+    })
+}
+
+impl Service {
+    fn is_current(&self, id: TimerId) -> bool {
+        let slot = usize::from(id.slot);
+        slot < MAX_TIMERS && self.generations[slot] == id.generation && self.slots[slot].is_some()
+    }
+
+    /// Move everything due at `now` into `due` (in deadline order),
+    /// rescheduling periodic timers and freeing one-shots.
+    fn collect_due(&mut self, now: Instant, due: &mut [Option<(fn(TimerId), TimerId)>]) {
+        let mut count = 0;
+        while let Some((slot, mut entry)) = self.earliest() {
+            if entry.deadline > now {
+                break;
+            }
+            let late = now.ticks() - entry.deadline.ticks();
+            self.max_lateness = self.max_lateness.max(late);
+            let id = TimerId {
+                slot: slot as u8,
+                generation: self.generations[slot],
+            };
+            due[count] = Some((entry.callback, id));
+            count += 1;
+            if entry.period == 0 {
+                self.slots[slot] = None;
+            } else {
+                // Skip any whole periods we've already missed.
+                let skipped = late / entry.period;
+                entry.missed = entry.missed.saturating_add(skipped as u32);
+                self.missed_deadlines = self.missed_deadlines.saturating_add(skipped as u32);
+                entry.deadline =
+                    Instant::from_ticks(entry.deadline.ticks() + entry.period * (skipped + 1));
+                self.slots[slot] = Some(entry);
+            }
+            if count == due.len() {
+                break;
+            }
+        }
+    }
+
+    /// The slot due first, and what's in it.
+    fn earliest(&self) -> Option<(usize, Entry)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, entry)| entry.map(|e| (slot, e)))
+            .min_by_key(|&(_, e)| e.deadline)
+    }
+
+    /// Set the hardware timer for the earliest deadline, or stop it if
+    /// there's nothing to wait for. A deadline too far off for the 32-bit
+    /// timer just gets an early, harmless, interrupt.
+    fn arm(&mut self) {
+        match self.earliest() {
+            Some((_, entry)) => {
+                let wait = entry.deadline.ticks().saturating_sub(time::now().ticks());
+                let ticks = wait.min(u64::from(u32::max_value())) as u32;
+                gptm::start_one_shot(&self.timer, ticks);
+            }
+            None => gptm::stop(&self.timer),
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
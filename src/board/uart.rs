@@ -0,0 +1,700 @@
+//! Interrupt-driven access to the UARTs: a ring-buffered driver, and futures
+//! for reading from `async` code (see `common::executor`).
+//!
+//! `BufferedUart` takes a UART which has been set up with
+//! `cpu::serial::Serial` and moves bytes between its FIFOs and a pair of
+//! ring buffers from the UART's interrupt, so nothing is lost while the
+//! application is busy elsewhere (up to the size of the receive buffer).
+//! The buffers are yours, so pick sizes to suit:
+//!
+//! ```ignore
+//! let rx_buffer = cortex_m::singleton!(: [u8; 256] = [0; 256]).unwrap();
+//! let tx_buffer = cortex_m::singleton!(: [u8; 64] = [0; 64]).unwrap();
+//! let serial = Serial::uart1(board.UART1, tx, rx, rts, cts, Bps(115200), ...);
+//! let mut uart = BufferedUart::new(serial, rx_buffer, tx_buffer);
+//! let n = uart.read(&mut line);
+//! uart.write_all(b"ok\r\n");
+//! ```
+//!
+//! Bytes received with a framing, parity or break error are dropped and
+//! counted (see `BufferedUart::errors`), as are bytes which arrive when the
+//! receive buffer is full. With RTS/CTS flow control - which on this chip
+//! only UART1 has, on PC4/PC5 or PF0/PF1, and which `Serial` turns on when
+//! it is given RTS and CTS pins - a full receive buffer instead leaves bytes
+//! in the FIFO, so the UART drops RTS and the far end waits. CTS is handled
+//! by the UART itself.
+//!
+//! For the futures, set the UART up as usual and then await `read_byte` or
+//! `read` on its `Port`. While a read is waiting, the UART's receive and
+//! receive-timeout interrupts are enabled; the interrupt handler turns them
+//! off again and wakes the task, which then empties the FIFO.
+//!
+//! Both need the UART's interrupt to call `on_interrupt`. The crate binds
+//! the vector of each port whose feature (`uart0` to `uart7`) is on; for any
+//! other port the vector is the application's, and its handler must call
+//! `on_interrupt(Port::UartN)`.
+//!
+//! ```ignore
+//! loop {
+//!     let byte = uart::read_byte(Port::Uart0).await?;
+//!     ...
+//! }
+//! ```
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::cell::{Cell, RefCell};
+use core::convert::Infallible;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use cortex_m::interrupt::{self, CriticalSection, Mutex};
+use cortex_m::peripheral::NVIC;
+use embedded_hal::serial;
+use nb;
+use tm4c123x_hal::serial::Serial;
+use tm4c123x_hal::tm4c123x::{self, uart0, Interrupt};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// One of the eight UARTs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Port {
+    /// UART0 (PA0/PA1, through the debug USB port)
+    Uart0,
+    /// UART1
+    Uart1,
+    /// UART2
+    Uart2,
+    /// UART3
+    Uart3,
+    /// UART4
+    Uart4,
+    /// UART5
+    Uart5,
+    /// UART6
+    Uart6,
+    /// UART7
+    Uart7,
+}
+
+/// Something wrong with a received byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The stop bit was missing.
+    Framing,
+    /// The parity bit was wrong.
+    Parity,
+    /// The line was held low for longer than a whole byte.
+    Break,
+    /// The FIFO was full, so bytes have been lost. It's reported after the
+    /// last byte which made it into the FIFO.
+    Overrun,
+}
+
+/// A UART whose interrupt fills and empties a pair of ring buffers. See the
+/// module documentation.
+///
+/// While a UART is buffered, don't use the futures on its `Port`.
+pub struct BufferedUart<UART, TX, RX, RTS, CTS> {
+    serial: Serial<UART, TX, RX, RTS, CTS>,
+    port: Port,
+}
+
+/// How many received bytes a `BufferedUart` has lost, and why.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCounts {
+    /// Bytes lost because the receive FIFO was full (the interrupt was held
+    /// off for too long)
+    pub overrun: u32,
+    /// Bytes dropped because the receive buffer was full
+    pub buffer_full: u32,
+    /// Bytes dropped because their stop bit was missing
+    pub framing: u32,
+    /// Bytes dropped because their parity bit was wrong
+    pub parity: u32,
+    /// Breaks (the line held low for longer than a whole byte)
+    pub breaks: u32,
+}
+
+/// One of `Board::UART0` to `Board::UART7`.
+pub trait Instance {
+    /// Which UART this is.
+    const PORT: Port;
+}
+
+/// A future which completes with the next byte received. See `read_byte`.
+#[derive(Debug)]
+pub struct ReadByte {
+    port: Port,
+}
+
+/// A future which completes once at least one byte has been received. See
+/// `read`.
+#[derive(Debug)]
+pub struct Read<'a> {
+    port: Port,
+    buffer: &'a mut [u8],
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// A byte queue in a buffer the application gave us.
+struct Ring {
+    buffer: &'static mut [u8],
+    start: usize,
+    len: usize,
+}
+
+/// The state of a `BufferedUart`, shared with its interrupt.
+struct Channel {
+    rx: Ring,
+    tx: Ring,
+    errors: ErrorCounts,
+    flow_control: bool,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// The task waiting on each UART.
+static WAKERS: Mutex<RefCell<[Option<Waker>; 8]>> = Mutex::new(RefCell::new([NO_WAKER; 8]));
+
+const NO_WAKER: Option<Waker> = None;
+
+/// The buffered UARTs.
+static CHANNELS: Mutex<RefCell<[Option<Channel>; 8]>> = Mutex::new(RefCell::new([NO_CHANNEL; 8]));
+
+const NO_CHANNEL: Option<Channel> = None;
+
+/// An error `read` found after some good bytes, for the next read to report.
+static DEFERRED: Mutex<Cell<[Option<Error>; 8]>> = Mutex::new(Cell::new([None; 8]));
+
+// UARTFR
+const FR_BUSY: u32 = 1 << 3;
+const FR_RXFE: u32 = 1 << 4;
+const FR_TXFF: u32 = 1 << 5;
+
+// UARTCTL
+const CTL_RTSEN: u32 = 1 << 14;
+
+// UARTDR
+const DR_FE: u32 = 1 << 8;
+const DR_PE: u32 = 1 << 9;
+const DR_BE: u32 = 1 << 10;
+const DR_OE: u32 = 1 << 11;
+
+// UARTIM and UARTICR
+const INT_RX: u32 = 1 << 4;
+const INT_TX: u32 = 1 << 5;
+const INT_RT: u32 = 1 << 6;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl<UART, TX, RX, RTS, CTS> BufferedUart<UART, TX, RX, RTS, CTS>
+where
+    UART: Instance,
+{
+    /// Buffer `serial`, receiving into `rx_buffer` and sending from
+    /// `tx_buffer`. Anything already in the receive FIFO is kept.
+    ///
+    /// Panics if the UART is already buffered (only possible if it was
+    /// stolen), or if either buffer is empty.
+    pub fn new(
+        serial: Serial<UART, TX, RX, RTS, CTS>,
+        rx_buffer: &'static mut [u8],
+        tx_buffer: &'static mut [u8],
+    ) -> BufferedUart<UART, TX, RX, RTS, CTS> {
+        assert!(!rx_buffer.is_empty() && !tx_buffer.is_empty());
+        let port = UART::PORT;
+        let uart = port.registers();
+        let channel = Channel {
+            rx: Ring::new(rx_buffer),
+            tx: Ring::new(tx_buffer),
+            errors: ErrorCounts::default(),
+            flow_control: uart.ctl.read().bits() & CTL_RTSEN != 0,
+        };
+        interrupt::free(|cs| {
+            let mut channels = CHANNELS.borrow(cs).borrow_mut();
+            assert!(channels[port.index()].is_none());
+            channels[port.index()] = Some(channel);
+            uart.icr
+                .write(|w| unsafe { w.bits(INT_RX | INT_TX | INT_RT) });
+            uart.im
+                .modify(|r, w| unsafe { w.bits(r.bits() | INT_RX | INT_RT) });
+        });
+        unsafe { NVIC::unmask(port.interrupt()) };
+        BufferedUart { serial, port }
+    }
+
+    /// Stop buffering, and give the UART back. Anything still waiting to be
+    /// sent or read is lost, so `flush` first.
+    pub fn free(self) -> Serial<UART, TX, RX, RTS, CTS> {
+        let uart = self.port.registers();
+        interrupt::free(|cs| {
+            uart.im
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(INT_RX | INT_TX | INT_RT)) });
+            CHANNELS.borrow(cs).borrow_mut()[self.port.index()] = None;
+        });
+        self.serial
+    }
+
+    /// Which UART this is.
+    pub fn port(&self) -> Port {
+        self.port
+    }
+
+    /// Take up to `buffer.len()` received bytes, without waiting. Returns
+    /// how many there were.
+    pub fn read(&mut self, buffer: &mut [u8]) -> usize {
+        self.with_channel(|channel| {
+            let mut count = 0;
+            while count < buffer.len() {
+                match channel.rx.pop() {
+                    Some(byte) => {
+                        buffer[count] = byte;
+                        count += 1;
+                    }
+                    None => break,
+                }
+            }
+            count
+        })
+    }
+
+    /// Take the next received byte, if there is one.
+    pub fn read_byte(&mut self) -> Option<u8> {
+        self.with_channel(|channel| channel.rx.pop())
+    }
+
+    /// How many received bytes are waiting to be read.
+    pub fn available(&self) -> usize {
+        self.with_channel(|channel| channel.rx.len)
+    }
+
+    /// Queue as much of `data` as there is room for, without waiting.
+    /// Returns how much that was.
+    pub fn write(&mut self, data: &[u8]) -> usize {
+        self.with_channel(|channel| {
+            let mut count = 0;
+            while count < data.len() && channel.tx.push(data[count]) {
+                count += 1;
+            }
+            count
+        })
+    }
+
+    /// Queue all of `data`, waiting for room if need be.
+    pub fn write_all(&mut self, data: &[u8]) {
+        let mut done = 0;
+        while done < data.len() {
+            done += self.write(&data[done..]);
+        }
+    }
+
+    /// Wait until everything queued has been sent.
+    pub fn flush(&mut self) {
+        while !self.is_idle() {}
+    }
+
+    /// The bytes lost so far.
+    pub fn errors(&self) -> ErrorCounts {
+        self.with_channel(|channel| channel.errors)
+    }
+
+    /// Set the error counts back to zero.
+    pub fn clear_errors(&mut self) {
+        self.with_channel(|channel| channel.errors = ErrorCounts::default())
+    }
+
+    /// Has everything queued been sent?
+    fn is_idle(&self) -> bool {
+        let uart = self.port.registers();
+        self.with_channel(|channel| channel.tx.len == 0) && uart.fr.read().bits() & FR_BUSY == 0
+    }
+
+    /// Work on the channel with the interrupt kept out, and then bring the
+    /// FIFOs and the interrupt mask up to date with what we did.
+    fn with_channel<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut Channel) -> T,
+    {
+        let port = self.port;
+        interrupt::free(|cs| {
+            let mut channels = CHANNELS.borrow(cs).borrow_mut();
+            let channel = channels[port.index()]
+                .as_mut()
+                .expect("buffered UART has no channel");
+            let result = f(channel);
+            service(port, channel);
+            result
+        })
+    }
+}
+
+impl<UART, TX, RX, RTS, CTS> serial::Read<u8> for BufferedUart<UART, TX, RX, RTS, CTS>
+where
+    UART: Instance,
+{
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Infallible> {
+        self.read_byte().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl<UART, TX, RX, RTS, CTS> serial::Write<u8> for BufferedUart<UART, TX, RX, RTS, CTS>
+where
+    UART: Instance,
+{
+    type Error = Infallible;
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+        match BufferedUart::write(self, &[byte]) {
+            0 => Err(nb::Error::WouldBlock),
+            _ => Ok(()),
+        }
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Infallible> {
+        if self.is_idle() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<UART, TX, RX, RTS, CTS> fmt::Write for BufferedUart<UART, TX, RX, RTS, CTS>
+where
+    UART: Instance,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// A future for the next byte received on `port`.
+pub fn read_byte(port: Port) -> ReadByte {
+    ReadByte { port }
+}
+
+/// A future which fills `buffer` with whatever has been received on `port`,
+/// waiting for at least one byte, and completes with how many bytes it got.
+/// A bad byte ends the read early: the bytes before it are returned, and the
+/// error comes from the next read on the port.
+pub fn read(port: Port, buffer: &mut [u8]) -> Read {
+    Read { port, buffer }
+}
+
+impl Future for ReadByte {
+    type Output = Result<u8, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<u8, Error>> {
+        let port = self.port;
+        match receive(port) {
+            Some(result) => Poll::Ready(result),
+            None => park(port, cx),
+        }
+    }
+}
+
+impl<'a> Future for Read<'a> {
+    type Output = Result<usize, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<usize, Error>> {
+        let port = self.port;
+        let mut count = 0;
+        while count < self.buffer.len() {
+            match receive(port) {
+                Some(Ok(byte)) => {
+                    self.buffer[count] = byte;
+                    count += 1;
+                }
+                Some(Err(e)) if count == 0 => return Poll::Ready(Err(e)),
+                Some(Err(e)) => {
+                    interrupt::free(|cs| defer(cs, port, e));
+                    break;
+                }
+                None => break,
+            }
+        }
+        if count > 0 || self.buffer.is_empty() {
+            Poll::Ready(Ok(count))
+        } else {
+            park(port, cx)
+        }
+    }
+}
+
+/// Move bytes between a buffered UART's FIFOs and buffers, or turn the
+/// receive interrupts off and wake the task waiting on an unbuffered one.
+/// Called from the crate's handler for the port with its `uartN` feature -
+/// otherwise from the application's.
+pub fn on_interrupt(port: Port) {
+    let buffered = interrupt::free(|cs| match CHANNELS.borrow(cs).borrow_mut()[port.index()] {
+        Some(ref mut channel) => {
+            service(port, channel);
+            true
+        }
+        None => false,
+    });
+    if buffered {
+        return;
+    }
+    let uart = port.registers();
+    uart.im
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(INT_RX | INT_RT)) });
+    uart.icr.write(|w| unsafe { w.bits(INT_RX | INT_RT) });
+    let waker = interrupt::free(|cs| WAKERS.borrow(cs).borrow_mut()[port.index()].take());
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+impl Port {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    pub(crate) fn registers(self) -> &'static uart0::RegisterBlock {
+        unsafe {
+            match self {
+                Port::Uart0 => &*tm4c123x::UART0::ptr(),
+                Port::Uart1 => &*tm4c123x::UART1::ptr(),
+                Port::Uart2 => &*tm4c123x::UART2::ptr(),
+                Port::Uart3 => &*tm4c123x::UART3::ptr(),
+                Port::Uart4 => &*tm4c123x::UART4::ptr(),
+                Port::Uart5 => &*tm4c123x::UART5::ptr(),
+                Port::Uart6 => &*tm4c123x::UART6::ptr(),
+                Port::Uart7 => &*tm4c123x::UART7::ptr(),
+            }
+        }
+    }
+
+    fn interrupt(self) -> Interrupt {
+        match self {
+            Port::Uart0 => Interrupt::UART0,
+            Port::Uart1 => Interrupt::UART1,
+            Port::Uart2 => Interrupt::UART2,
+            Port::Uart3 => Interrupt::UART3,
+            Port::Uart4 => Interrupt::UART4,
+            Port::Uart5 => Interrupt::UART5,
+            Port::Uart6 => Interrupt::UART6,
+            Port::Uart7 => Interrupt::UART7,
+        }
+    }
+}
+
+impl Instance for tm4c123x::UART0 {
+    const PORT: Port = Port::Uart0;
+}
+
+impl Instance for tm4c123x::UART1 {
+    const PORT: Port = Port::Uart1;
+}
+
+impl Instance for tm4c123x::UART2 {
+    const PORT: Port = Port::Uart2;
+}
+
+impl Instance for tm4c123x::UART3 {
+    const PORT: Port = Port::Uart3;
+}
+
+impl Instance for tm4c123x::UART4 {
+    const PORT: Port = Port::Uart4;
+}
+
+impl Instance for tm4c123x::UART5 {
+    const PORT: Port = Port::Uart5;
+}
+
+impl Instance for tm4c123x::UART6 {
+    const PORT: Port = Port::Uart6;
+}
+
+impl Instance for tm4c123x::UART7 {
+    const PORT: Port = Port::Uart7;
+}
+
+impl Ring {
+    fn new(buffer: &'static mut [u8]) -> Ring {
+        Ring {
+            buffer,
+            start: 0,
+            len: 0,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == self.buffer.len()
+    }
+
+    /// Add a byte to the end, unless we're full.
+    fn push(&mut self, byte: u8) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        let end = (self.start + self.len) % self.buffer.len();
+        self.buffer[end] = byte;
+        self.len += 1;
+        true
+    }
+
+    /// Take the byte at the front.
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.buffer[self.start];
+        self.start = (self.start + 1) % self.buffer.len();
+        self.len -= 1;
+        Some(byte)
+    }
+}
+
+/// Empty the receive FIFO into the receive buffer and fill the transmit
+/// FIFO from the transmit buffer, then ask for an interrupt when there's
+/// more of either to do.
+fn service(port: Port, channel: &mut Channel) {
+    let uart = port.registers();
+    uart.icr
+        .write(|w| unsafe { w.bits(INT_RX | INT_TX | INT_RT) });
+
+    while uart.fr.read().bits() & FR_RXFE == 0 {
+        if channel.flow_control && channel.rx.is_full() {
+            // Leave it in the FIFO, and let RTS hold the sender off.
+            break;
+        }
+        let data = uart.dr.read().bits();
+        let errors = &mut channel.errors;
+        if data & DR_OE != 0 {
+            // The byte is fine; it's the ones after it that were lost.
+            errors.overrun = errors.overrun.wrapping_add(1);
+        }
+        if data & DR_BE != 0 {
+            errors.breaks = errors.breaks.wrapping_add(1);
+        } else if data & DR_PE != 0 {
+            errors.parity = errors.parity.wrapping_add(1);
+        } else if data & DR_FE != 0 {
+            errors.framing = errors.framing.wrapping_add(1);
+        } else if !channel.rx.push(data as u8) {
+            errors.buffer_full = errors.buffer_full.wrapping_add(1);
+        }
+    }
+
+    while uart.fr.read().bits() & FR_TXFF == 0 {
+        match channel.tx.pop() {
+            Some(byte) => uart.dr.write(|w| unsafe { w.bits(u32::from(byte)) }),
+            None => break,
+        }
+    }
+
+    let mut mask = 0;
+    if !(channel.flow_control && channel.rx.is_full()) {
+        mask |= INT_RX | INT_RT;
+    }
+    if channel.tx.len != 0 {
+        mask |= INT_TX;
+    }
+    uart.im
+        .modify(|r, w| unsafe { w.bits((r.bits() & !(INT_RX | INT_TX | INT_RT)) | mask) });
+}
+
+/// Take a byte from the receive FIFO, if there is one - after any error an
+/// earlier `read` put off.
+fn receive(port: Port) -> Option<Result<u8, Error>> {
+    if let Some(e) = interrupt::free(|cs| take_deferred(cs, port)) {
+        return Some(Err(e));
+    }
+    let uart = port.registers();
+    if uart.fr.read().bits() & FR_RXFE != 0 {
+        return None;
+    }
+    let data = uart.dr.read().bits();
+    if data & DR_OE != 0 {
+        // The byte is fine; it's the ones after it that were lost, so say
+        // so on the next read.
+        interrupt::free(|cs| defer(cs, port, Error::Overrun));
+    }
+    Some(if data & DR_BE != 0 {
+        Err(Error::Break)
+    } else if data & DR_PE != 0 {
+        Err(Error::Parity)
+    } else if data & DR_FE != 0 {
+        Err(Error::Framing)
+    } else {
+        Ok(data as u8)
+    })
+}
+
+fn defer(cs: &CriticalSection, port: Port, error: Error) {
+    let deferred = DEFERRED.borrow(cs);
+    let mut errors = deferred.get();
+    errors[port.index()] = Some(error);
+    deferred.set(errors);
+}
+
+fn take_deferred(cs: &CriticalSection, port: Port) -> Option<Error> {
+    let deferred = DEFERRED.borrow(cs);
+    let mut errors = deferred.get();
+    let error = errors[port.index()].take();
+    deferred.set(errors);
+    error
+}
+
+/// Register the task and turn the receive interrupts on. A byte arriving
+/// while we do this still raises the interrupt, once it's unmasked.
+fn park<T>(port: Port, cx: &mut Context) -> Poll<T> {
+    let uart = port.registers();
+    interrupt::free(|cs| {
+        WAKERS.borrow(cs).borrow_mut()[port.index()] = Some(cx.waker().clone());
+        uart.im
+            .modify(|r, w| unsafe { w.bits(r.bits() | INT_RX | INT_RT) });
+    });
+    unsafe { NVIC::unmask(port.interrupt()) };
+    Poll::Pending
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -6,8 +6,11 @@
 //
 // ****************************************************************************
 
-use board;
+use crate::board;
+use crate::board::crash::{self, CrashKind};
+use core::fmt::Write;
 use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 // ****************************************************************************
 //
@@ -39,7 +42,9 @@ use core::panic::PanicInfo;
 //
 // ****************************************************************************
 
-// None
+/// Set on the first panic, so a panic while reporting a panic goes straight
+/// to the LED.
+static PANICKING: AtomicBool = AtomicBool::new(false);
 
 // ****************************************************************************
 //
@@ -66,8 +71,15 @@ pub extern "C" fn _Unwind_Resume() -> ! {
     board::panic();
 }
 
+/// Reports the panic message and location on UART0, then flashes the LED.
 #[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
+fn panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    if !PANICKING.swap(true, Ordering::Relaxed) {
+        crash::record(CrashKind::Panic, 0, None, format_args!("{}", info));
+        // e.g. "panicked at 'oh no', examples/chess.rs:123:9"
+        let _ = writeln!(board::fault_uart(), "{}", info);
+    }
     board::panic();
 }
 
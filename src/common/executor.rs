@@ -0,0 +1,229 @@
+//! A small cooperative executor, for running `async` code on the board.
+//!
+//! Tasks are futures the application owns (pinned on the stack, usually with
+//! `core::pin::pin!`), so nothing is allocated. Each task has a bit in a
+//! global ready mask; its `Waker` sets the bit, which is safe from any
+//! interrupt handler. When no task is ready the core sleeps in `wfi`, so an
+//! idle application costs nothing but the interrupts that wake it.
+//!
+//! The crate provides futures for the things an application waits on:
+//! `board::timers::sleep`, `board::edges::edge` and `board::uart::read_byte`.
+//!
+//! ```ignore
+//! let mut blink = pin!(blink_forever());
+//! let mut echo = pin!(echo(Port::Uart0));
+//! let mut executor = Executor::new();
+//! executor.spawn(blink.as_mut())?;
+//! executor.spawn(echo.as_mut())?;
+//! executor.run();
+//! ```
+//!
+//! There's only one ready mask, so only one `Executor` (or `block_on`) can be
+//! running at a time. An `async fn main` (see `stellaris_launchpad::main`) is
+//! run with `block_on`.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::future::Future;
+use core::pin::{pin, Pin};
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use cortex_m::interrupt;
+use cortex_m::register;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A task: a pinned future that runs until it completes.
+pub type Task<'a> = Pin<&'a mut dyn Future<Output = ()>>;
+
+/// Runs up to `MAX_TASKS` tasks, polling each only when it has been woken.
+pub struct Executor<'a> {
+    tasks: [Option<Task<'a>>; MAX_TASKS],
+}
+
+/// `spawn` was called with `MAX_TASKS` tasks already running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooManyTasks;
+
+/// The future returned by `yield_now`.
+#[derive(Debug)]
+pub struct YieldNow {
+    yielded: bool,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// How many tasks an `Executor` can run at once.
+pub const MAX_TASKS: usize = 16;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// One bit per task slot, set when the task should be polled.
+static READY: AtomicU32 = AtomicU32::new(0);
+
+/// The ready bit `block_on` uses, clear of the task slots.
+const BLOCK_ON_SLOT: usize = 31;
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(clone_waker, wake, wake, drop_waker);
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl<'a> Executor<'a> {
+    /// An executor with no tasks.
+    pub fn new() -> Executor<'a> {
+        Executor {
+            tasks: Default::default(),
+        }
+    }
+
+    /// Add a task. It's first polled when `run` is called.
+    pub fn spawn(&mut self, task: Task<'a>) -> Result<(), TooManyTasks> {
+        let slot = self
+            .tasks
+            .iter()
+            .position(Option::is_none)
+            .ok_or(TooManyTasks)?;
+        self.tasks[slot] = Some(task);
+        READY.fetch_or(1 << slot, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Run the tasks until they have all completed, sleeping whenever none
+    /// of them is ready.
+    pub fn run(&mut self) {
+        let mask = (1 << MAX_TASKS) - 1;
+        while self.tasks.iter().any(Option::is_some) {
+            let ready = wait_for(mask);
+            for (slot, task) in self.tasks.iter_mut().enumerate() {
+                if ready & (1 << slot) == 0 {
+                    continue;
+                }
+                let done = match *task {
+                    Some(ref mut future) => poll(future.as_mut(), slot).is_ready(),
+                    None => false,
+                };
+                if done {
+                    *task = None;
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Default for Executor<'a> {
+    fn default() -> Executor<'a> {
+        Executor::new()
+    }
+}
+
+/// Run one future to completion, sleeping while it waits, and return its
+/// output.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    READY.fetch_or(1 << BLOCK_ON_SLOT, Ordering::Relaxed);
+    loop {
+        wait_for(1 << BLOCK_ON_SLOT);
+        if let Poll::Ready(output) = poll(future.as_mut(), BLOCK_ON_SLOT) {
+            return output;
+        }
+    }
+}
+
+/// Let the other tasks run before carrying on.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn poll<F: Future + ?Sized>(future: Pin<&mut F>, slot: usize) -> Poll<F::Output> {
+    // The waker only holds the slot number, so there's nothing to free.
+    let waker = unsafe { Waker::from_raw(RawWaker::new(slot as *const (), &VTABLE)) };
+    future.poll(&mut Context::from_waker(&waker))
+}
+
+/// Sleep until at least one of the `mask` bits is ready, then clear those
+/// bits and return them. Interrupts are left masked if they were masked to
+/// begin with (though then only a task can wake another).
+fn wait_for(mask: u32) -> u32 {
+    let primask = register::primask::read();
+    loop {
+        // With interrupts masked, a wake can't slip in between the check and
+        // the WFI. WFI still wakes on the interrupt, which runs as soon as
+        // we unmask.
+        interrupt::disable();
+        let ready = READY.fetch_and(!mask, Ordering::Relaxed) & mask;
+        if ready == 0 {
+            cortex_m::asm::wfi();
+        }
+        if primask.is_active() {
+            unsafe { interrupt::enable() };
+        }
+        if ready != 0 {
+            return ready;
+        }
+    }
+}
+
+unsafe fn clone_waker(data: *const ()) -> RawWaker {
+    RawWaker::new(data, &VTABLE)
+}
+
+unsafe fn wake(data: *const ()) {
+    READY.fetch_or(1 << (data as usize), Ordering::Relaxed);
+}
+
+unsafe fn drop_waker(_data: *const ()) {}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
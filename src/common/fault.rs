@@ -0,0 +1,458 @@
+//! Decodes the Cortex-M4 fault status registers, and reports faults.
+//!
+//! cortex-m-rt only hands the stacked exception frame to the HardFault
+//! handler, and runs it on whatever stack faulted, so HardFault, MemManage,
+//! BusFault and UsageFault all go through a small assembly trampoline. It
+//! finds the frame (on MSP or PSP, according to EXC_RETURN), moves MSP back
+//! to the top of the stack if the stack has overflowed, and passes the frame
+//! on to `stellaris_configurable_fault`.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::fmt::{self, Write};
+
+use cortex_m::peripheral::{DCB, SCB};
+use cortex_m_rt::ExceptionFrame;
+
+use crate::board;
+use crate::board::crash::{self, CrashKind};
+use crate::common::stack;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A snapshot of the fault status and address registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaultStatus {
+    /// Configurable Fault Status Register (MMFSR, BFSR and UFSR).
+    pub cfsr: u32,
+    /// HardFault Status Register.
+    pub hfsr: u32,
+    /// MemManage Fault Address Register. Only meaningful if
+    /// `FaultCause::MemManageAddressValid` is set.
+    pub mmfar: u32,
+    /// BusFault Address Register. Only meaningful if
+    /// `FaultCause::BusFaultAddressValid` is set.
+    pub bfar: u32,
+}
+
+/// One of the reasons the fault status registers can give for a fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultCause {
+    /// MemManage: instruction fetch from a location that doesn't permit
+    /// execution (IACCVIOL).
+    InstructionAccessViolation,
+    /// MemManage: load or store to a location that doesn't permit it
+    /// (DACCVIOL).
+    DataAccessViolation,
+    /// MemManage: fault while unstacking on exception return (MUNSTKERR).
+    MemManageOnUnstacking,
+    /// MemManage: fault while stacking on exception entry (MSTKERR).
+    MemManageOnStacking,
+    /// MemManage: fault during lazy floating-point state preservation
+    /// (MLSPERR).
+    MemManageOnLazyFpState,
+    /// MMFAR holds the faulting address (MMARVALID).
+    MemManageAddressValid,
+    /// BusFault: error on an instruction fetch (IBUSERR).
+    InstructionBusError,
+    /// BusFault: error on a data access, and the PC is exact (PRECISERR).
+    PreciseBusError,
+    /// BusFault: error on a buffered write - the stacked PC is somewhere
+    /// after the instruction responsible (IMPRECISERR).
+    ImpreciseBusError,
+    /// BusFault: error while unstacking on exception return (UNSTKERR).
+    BusErrorOnUnstacking,
+    /// BusFault: error while stacking on exception entry (STKERR).
+    BusErrorOnStacking,
+    /// BusFault: error during lazy floating-point state preservation
+    /// (LSPERR).
+    BusErrorOnLazyFpState,
+    /// BFAR holds the faulting address (BFARVALID).
+    BusFaultAddressValid,
+    /// UsageFault: undefined instruction (UNDEFINSTR).
+    UndefinedInstruction,
+    /// UsageFault: attempt to leave Thumb state, e.g. a branch to an even
+    /// address (INVSTATE).
+    InvalidState,
+    /// UsageFault: bad EXC_RETURN value loaded into the PC (INVPC).
+    InvalidExcReturn,
+    /// UsageFault: coprocessor (e.g. FPU) instruction with the coprocessor
+    /// disabled (NOCP).
+    NoCoprocessor,
+    /// UsageFault: unaligned access, with unaligned trapping enabled
+    /// (UNALIGNED).
+    UnalignedAccess,
+    /// UsageFault: divide by zero, with divide-by-zero trapping enabled
+    /// (DIVBYZERO).
+    DivideByZero,
+    /// HardFault: bus error reading the vector table (VECTTBL).
+    VectorTableRead,
+    /// HardFault: a configurable fault escalated because its handler was
+    /// disabled or couldn't run (FORCED).
+    Forced,
+    /// HardFault: a debug event happened with no debugger (DEBUGEVT).
+    DebugEvent,
+}
+
+/// Iterates over the causes recorded in a `FaultStatus`.
+pub struct FaultCauses {
+    status: FaultStatus,
+    index: usize,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// Which register a cause's bit lives in.
+#[derive(Clone, Copy)]
+enum Register {
+    Cfsr,
+    Hfsr,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// Every cause we decode, and where to find it.
+static CAUSES: [(Register, u32, FaultCause); 22] = [
+    (
+        Register::Cfsr,
+        1 << 0,
+        FaultCause::InstructionAccessViolation,
+    ),
+    (Register::Cfsr, 1 << 1, FaultCause::DataAccessViolation),
+    (Register::Cfsr, 1 << 3, FaultCause::MemManageOnUnstacking),
+    (Register::Cfsr, 1 << 4, FaultCause::MemManageOnStacking),
+    (Register::Cfsr, 1 << 5, FaultCause::MemManageOnLazyFpState),
+    (Register::Cfsr, 1 << 7, FaultCause::MemManageAddressValid),
+    (Register::Cfsr, 1 << 8, FaultCause::InstructionBusError),
+    (Register::Cfsr, 1 << 9, FaultCause::PreciseBusError),
+    (Register::Cfsr, 1 << 10, FaultCause::ImpreciseBusError),
+    (Register::Cfsr, 1 << 11, FaultCause::BusErrorOnUnstacking),
+    (Register::Cfsr, 1 << 12, FaultCause::BusErrorOnStacking),
+    (Register::Cfsr, 1 << 13, FaultCause::BusErrorOnLazyFpState),
+    (Register::Cfsr, 1 << 15, FaultCause::BusFaultAddressValid),
+    (Register::Cfsr, 1 << 16, FaultCause::UndefinedInstruction),
+    (Register::Cfsr, 1 << 17, FaultCause::InvalidState),
+    (Register::Cfsr, 1 << 18, FaultCause::InvalidExcReturn),
+    (Register::Cfsr, 1 << 19, FaultCause::NoCoprocessor),
+    (Register::Cfsr, 1 << 24, FaultCause::UnalignedAccess),
+    (Register::Cfsr, 1 << 25, FaultCause::DivideByZero),
+    (Register::Hfsr, 1 << 1, FaultCause::VectorTableRead),
+    (Register::Hfsr, 1 << 30, FaultCause::Forced),
+    (Register::Hfsr, 1 << 31, FaultCause::DebugEvent),
+];
+
+// Distinguishes the three trampoline entry points. Must match the `movs`
+// in the assembly below.
+const TRAMPOLINE_MEMORY_MANAGEMENT: u32 = 1;
+const TRAMPOLINE_BUS_FAULT: u32 = 2;
+const TRAMPOLINE_USAGE_FAULT: u32 = 3;
+const TRAMPOLINE_HARD_FAULT: u32 = 4;
+
+// SHCSR
+const SHCSR_MEMFAULTENA: u32 = 1 << 16;
+const SHCSR_BUSFAULTENA: u32 = 1 << 17;
+const SHCSR_USGFAULTENA: u32 = 1 << 18;
+
+// CCR
+const CCR_UNALIGN_TRP: u32 = 1 << 3;
+const CCR_DIV_0_TRP: u32 = 1 << 4;
+
+core::arch::global_asm!(
+    ".section .text.MemoryManagement, \"ax\"",
+    ".global MemoryManagement",
+    ".type MemoryManagement, %function",
+    ".thumb_func",
+    "MemoryManagement:",
+    "    movs r1, #1",
+    "    b stellaris_fault_trampoline",
+    "",
+    ".section .text.BusFault, \"ax\"",
+    ".global BusFault",
+    ".type BusFault, %function",
+    ".thumb_func",
+    "BusFault:",
+    "    movs r1, #2",
+    "    b stellaris_fault_trampoline",
+    "",
+    ".section .text.UsageFault, \"ax\"",
+    ".global UsageFault",
+    ".type UsageFault, %function",
+    ".thumb_func",
+    "UsageFault:",
+    "    movs r1, #3",
+    "    b stellaris_fault_trampoline",
+    "",
+    // cortex-m-rt's HardFaultTrampoline branches here, in place of a
+    // `#[exception] fn HardFault`.
+    ".section .text.HardFault, \"ax\"",
+    ".global HardFault",
+    ".type HardFault, %function",
+    ".thumb_func",
+    "HardFault:",
+    "    movs r1, #4",
+    "    b stellaris_fault_trampoline",
+    "",
+    ".section .text.stellaris_fault_trampoline, \"ax\"",
+    ".type stellaris_fault_trampoline, %function",
+    ".thumb_func",
+    "stellaris_fault_trampoline:",
+    // Bit 2 of EXC_RETURN says which stack the frame was pushed on to.
+    "    tst lr, #4",
+    "    ite eq",
+    "    mrseq r0, MSP",
+    "    mrsne r0, PSP",
+    // If the main stack has (nearly) run out, as it will have after hitting
+    // the stack guard, start again from the top so the handler has room.
+    // The frame is down at the bottom, out of the way.
+    "    mrs r2, MSP",
+    "    ldr r3, =(_stack_guard_end + 512)",
+    "    cmp r2, r3",
+    "    bhs 1f",
+    "    ldr r2, =_stack_start",
+    "    msr MSP, r2",
+    "1:",
+    "    b stellaris_configurable_fault",
+    ".ltorg",
+);
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl FaultStatus {
+    /// Read the fault registers.
+    pub fn read() -> FaultStatus {
+        let scb = unsafe { &*SCB::ptr() };
+        FaultStatus {
+            cfsr: scb.cfsr.read(),
+            hfsr: scb.hfsr.read(),
+            mmfar: scb.mmfar.read(),
+            bfar: scb.bfar.read(),
+        }
+    }
+
+    /// Is this cause flagged?
+    pub fn has(&self, cause: FaultCause) -> bool {
+        self.causes().any(|c| c == cause)
+    }
+
+    /// All the causes flagged in these registers.
+    pub fn causes(&self) -> FaultCauses {
+        FaultCauses {
+            status: *self,
+            index: 0,
+        }
+    }
+
+    /// The address that caused a MemManage fault, if the hardware captured
+    /// it.
+    pub fn memory_management_address(&self) -> Option<u32> {
+        if self.has(FaultCause::MemManageAddressValid) {
+            Some(self.mmfar)
+        } else {
+            None
+        }
+    }
+
+    /// The address that caused a BusFault, if the hardware captured it.
+    pub fn bus_fault_address(&self) -> Option<u32> {
+        if self.has(FaultCause::BusFaultAddressValid) {
+            Some(self.bfar)
+        } else {
+            None
+        }
+    }
+
+    /// Did the stack overflow? Either pushing the exception frame failed, or
+    /// something wrote to the stack guard region.
+    pub fn is_stack_overflow(&self) -> bool {
+        self.has(FaultCause::MemManageOnStacking)
+            || self.has(FaultCause::BusErrorOnStacking)
+            || self
+                .memory_management_address()
+                .map_or(false, stack::is_guard_address)
+    }
+}
+
+impl Iterator for FaultCauses {
+    type Item = FaultCause;
+
+    fn next(&mut self) -> Option<FaultCause> {
+        while let Some(&(register, mask, cause)) = CAUSES.get(self.index) {
+            self.index += 1;
+            let value = match register {
+                Register::Cfsr => self.status.cfsr,
+                Register::Hfsr => self.status.hfsr,
+            };
+            if value & mask != 0 {
+                return Some(cause);
+            }
+        }
+        None
+    }
+}
+
+impl fmt::Display for FaultCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            FaultCause::InstructionAccessViolation => "instruction fetch from a no-execute region",
+            FaultCause::DataAccessViolation => "data access to a protected region",
+            FaultCause::MemManageOnUnstacking => "MPU fault unstacking on exception return",
+            FaultCause::MemManageOnStacking => "MPU fault stacking on exception entry",
+            FaultCause::MemManageOnLazyFpState => "MPU fault saving lazy FP state",
+            FaultCause::MemManageAddressValid => "MMFAR valid",
+            FaultCause::InstructionBusError => "bus error on instruction fetch",
+            FaultCause::PreciseBusError => "precise bus error on data access",
+            FaultCause::ImpreciseBusError => "imprecise bus error (PC is approximate)",
+            FaultCause::BusErrorOnUnstacking => "bus error unstacking on exception return",
+            FaultCause::BusErrorOnStacking => "bus error stacking on exception entry",
+            FaultCause::BusErrorOnLazyFpState => "bus error saving lazy FP state",
+            FaultCause::BusFaultAddressValid => "BFAR valid",
+            FaultCause::UndefinedInstruction => "undefined instruction",
+            FaultCause::InvalidState => "invalid state (branch to an ARM-mode address?)",
+            FaultCause::InvalidExcReturn => "invalid EXC_RETURN",
+            FaultCause::NoCoprocessor => "coprocessor (FPU) disabled",
+            FaultCause::UnalignedAccess => "unaligned access",
+            FaultCause::DivideByZero => "divide by zero",
+            FaultCause::VectorTableRead => "bus error reading the vector table",
+            FaultCause::Forced => "escalated from a configurable fault",
+            FaultCause::DebugEvent => "debug event",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Route MemManage, BusFault and UsageFault to their own handlers rather
+/// than escalating them to HardFault, and optionally trap on divide by zero
+/// and unaligned accesses. Called from `Board::new`.
+pub(crate) fn enable_traps(scb: &SCB, divide_by_zero: bool, unaligned: bool) {
+    unsafe {
+        scb.shcsr
+            .modify(|r| r | SHCSR_MEMFAULTENA | SHCSR_BUSFAULTENA | SHCSR_USGFAULTENA);
+        scb.ccr.modify(|r| {
+            let r = if divide_by_zero {
+                r | CCR_DIV_0_TRP
+            } else {
+                r & !CCR_DIV_0_TRP
+            };
+            if unaligned {
+                r | CCR_UNALIGN_TRP
+            } else {
+                r & !CCR_UNALIGN_TRP
+            }
+        });
+    }
+}
+
+/// Write a human-readable description of a fault.
+pub fn write_report<W: Write>(
+    w: &mut W,
+    kind: CrashKind,
+    frame: &ExceptionFrame,
+    status: &FaultStatus,
+) -> fmt::Result {
+    writeln!(w, "*** {:?} ***", kind)?;
+    if status.is_stack_overflow() {
+        writeln!(w, "Stack overflow!")?;
+    }
+    writeln!(
+        w,
+        "PC  = {:#010x}  LR  = {:#010x}  xPSR = {:#010x}",
+        frame.pc, frame.lr, frame.xpsr
+    )?;
+    writeln!(
+        w,
+        "R0  = {:#010x}  R1  = {:#010x}  R2   = {:#010x}  R3 = {:#010x}  R12 = {:#010x}",
+        frame.r0, frame.r1, frame.r2, frame.r3, frame.r12
+    )?;
+    writeln!(
+        w,
+        "CFSR = {:#010x}  HFSR = {:#010x}",
+        status.cfsr, status.hfsr
+    )?;
+    for cause in status.causes() {
+        match cause {
+            FaultCause::MemManageAddressValid | FaultCause::BusFaultAddressValid => {}
+            _ => writeln!(w, " - {}", cause)?,
+        }
+    }
+    if let Some(address) = status.memory_management_address() {
+        writeln!(w, " - faulting address (MMFAR) {:#010x}", address)?;
+    }
+    if let Some(address) = status.bus_fault_address() {
+        writeln!(w, " - faulting address (BFAR) {:#010x}", address)?;
+    }
+    Ok(())
+}
+
+/// Record, report and halt on a fault.
+pub(crate) fn handle(kind: CrashKind, frame: &ExceptionFrame) -> ! {
+    let status = FaultStatus::read();
+    let message = if status.is_stack_overflow() {
+        "stack overflow"
+    } else {
+        ""
+    };
+    crash::record(kind, 0, Some(frame), format_args!("{}", message));
+    let _ = write_report(&mut board::fault_uart(), kind, frame, &status);
+    if DCB::is_debugger_attached() {
+        cortex_m::asm::bkpt();
+    }
+    board::panic();
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// Called by the assembly trampoline for HardFault and the three
+/// configurable faults.
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn stellaris_configurable_fault(
+    frame: &ExceptionFrame,
+    trampoline: u32,
+) -> ! {
+    let kind = match trampoline {
+        TRAMPOLINE_MEMORY_MANAGEMENT => CrashKind::MemoryManagement,
+        TRAMPOLINE_BUS_FAULT => CrashKind::BusFault,
+        TRAMPOLINE_USAGE_FAULT => CrashKind::UsageFault,
+        TRAMPOLINE_HARD_FAULT => CrashKind::HardFault,
+        _ => CrashKind::Unknown,
+    };
+    handle(kind, frame)
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,167 @@
+//! The global allocator, and its out-of-memory handler. Only present with
+//! the `alloc` feature (on by default).
+//!
+//! The heap runs from the end of `.bss` up to the bottom of the stack,
+//! unless `STELLARIS_HEAP_SIZE` was set at build time - see `memory.x.in`.
+//! Call [`stats`] to see how it's being used.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+extern crate alloc_cortex_m;
+
+use self::alloc_cortex_m::CortexMHeap;
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::fmt::Write;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::board;
+use crate::board::crash::{self, CrashKind};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A snapshot of the heap's usage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapStats {
+    /// The size of the heap, in bytes.
+    pub size: usize,
+    /// The number of bytes currently allocated.
+    pub used: usize,
+    /// The number of bytes currently free.
+    pub free: usize,
+    /// The most bytes that have ever been allocated at once.
+    pub high_water: usize,
+    /// The number of allocations that have failed.
+    pub failed_allocations: usize,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+/// Wraps the allocator to keep count of how it's being used.
+struct Heap {
+    inner: CortexMHeap,
+    high_water: AtomicUsize,
+    failed_allocations: AtomicUsize,
+}
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+#[global_allocator]
+static ALLOCATOR: Heap = Heap {
+    inner: CortexMHeap::empty(),
+    high_water: AtomicUsize::new(0),
+    failed_allocations: AtomicUsize::new(0),
+};
+
+extern "C" {
+    // Defined in memory.x. Only the address of this symbol is meaningful.
+    static _heap_size: u8;
+}
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// The size of the heap in bytes, as set at link time.
+pub fn size() -> usize {
+    unsafe { &_heap_size as *const u8 as usize }
+}
+
+/// Get the current heap usage.
+pub fn stats() -> HeapStats {
+    HeapStats {
+        size: size(),
+        used: ALLOCATOR.inner.used(),
+        free: ALLOCATOR.inner.free(),
+        high_water: ALLOCATOR.high_water.load(Ordering::Relaxed),
+        failed_allocations: ALLOCATOR.failed_allocations.load(Ordering::Relaxed),
+    }
+}
+
+/// Hand the heap region to the allocator. Must be called once, before
+/// anything allocates.
+pub(crate) unsafe fn init() {
+    ALLOCATOR
+        .inner
+        .init(cortex_m_rt::heap_start() as usize, size());
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+unsafe impl GlobalAlloc for Heap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // Sample the usage in the same critical section as the allocation,
+        // so the high-water mark can't miss an interrupt's allocation.
+        cortex_m::interrupt::free(|_| {
+            let ptr = self.inner.alloc(layout);
+            if ptr.is_null() {
+                self.failed_allocations.fetch_add(1, Ordering::Relaxed);
+            } else {
+                self.high_water
+                    .fetch_max(self.inner.used(), Ordering::Relaxed);
+            }
+            ptr
+        })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout)
+    }
+}
+
+/// Called when an allocation fails. Reports what was asked for, and the state
+/// of the heap, on UART0 and then flashes the LED.
+#[alloc_error_handler]
+fn oom(layout: Layout) -> ! {
+    cortex_m::interrupt::disable();
+    crash::record(
+        CrashKind::OutOfMemory,
+        0,
+        None,
+        format_args!("{} bytes, align {}", layout.size(), layout.align()),
+    );
+    let _ = writeln!(
+        board::fault_uart(),
+        "Out of memory allocating {} bytes (align {}): {:?}",
+        layout.size(),
+        layout.align(),
+        stats()
+    );
+    board::panic();
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -8,6 +8,12 @@
 
 // pub mod memory;
 pub mod builtins;
+pub mod executor;
+pub mod fault;
+#[cfg(feature = "alloc")]
+pub mod heap;
+pub mod once;
+pub mod stack;
 pub mod startup;
 
 // ****************************************************************************
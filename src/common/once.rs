@@ -0,0 +1,120 @@
+//! A cell that is written exactly once during start-up, and can then be read
+//! from anywhere - including interrupt and fault handlers - without `unsafe`.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// Holds a value of type `T` which is set once and never changed.
+///
+/// Reads before the value is set return `None`, as do reads which race with
+/// the (single) write. Once a read has returned `Some`, every subsequent read
+/// returns the same value.
+pub struct WriteOnce<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const EMPTY: u8 = 0;
+const WRITING: u8 = 1;
+const READY: u8 = 2;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+impl<T> WriteOnce<T> {
+    /// Create an empty cell, suitable for use in a `static`.
+    pub const fn new() -> WriteOnce<T> {
+        WriteOnce {
+            state: AtomicU8::new(EMPTY),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Store the value. If the cell has already been written (or is being
+    /// written by an interrupted context), the value is handed back.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        if self
+            .state
+            .compare_exchange(EMPTY, WRITING, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(value);
+        }
+        // We won the race to WRITING, so nobody else can be touching `value`.
+        unsafe {
+            (*self.value.get()).as_mut_ptr().write(value);
+        }
+        self.state.store(READY, Ordering::Release);
+        Ok(())
+    }
+
+    /// Get the value, if it has been set.
+    pub fn get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) == READY {
+            // READY is only stored after `value` is initialised, and it is
+            // never written again.
+            Some(unsafe { &*(*self.value.get()).as_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Has the value been set?
+    pub fn is_set(&self) -> bool {
+        self.get().is_some()
+    }
+}
+
+unsafe impl<T: Send + Sync> Sync for WriteOnce<T> {}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,175 @@
+//! Stack overflow detection and stack usage measurement.
+//!
+//! The stack is the top `_stack_size` bytes of RAM, growing down towards the
+//! heap (see `memory.x.in`). Start-up paints the unused stack with a known
+//! pattern, so `stats()` can later find the deepest point it reached. If
+//! `BoardConfig::stack_guard` is set, the MPU also makes the lowest
+//! `GUARD_SIZE` bytes read-only, so an overflow raises a MemManage fault
+//! (reported as a stack overflow) rather than quietly corrupting the heap.
+//!
+//! The guard is 256 bytes unless `STELLARIS_STACK_GUARD_SIZE` (a power of two,
+//! at least 32) was set at build time, and the stack size must be a multiple
+//! of it. A function whose locals are bigger than the guard can step straight
+//! over it and write to the heap without faulting, so if the application has
+//! big stack frames (large arrays or buffers on the stack), make the guard
+//! bigger than the biggest of them.
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::ptr;
+
+use cortex_m::peripheral::MPU;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// How much of the stack has been used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackStats {
+    /// The size of the stack in bytes, as set at link time (including the
+    /// guard region).
+    pub size: usize,
+    /// The number of bytes in use right now.
+    pub used: usize,
+    /// The most bytes that have ever been in use, as far as the paint can
+    /// tell.
+    pub high_water: usize,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The size of the MPU guard region at the bottom of the stack, in bytes. Set
+/// by `STELLARIS_STACK_GUARD_SIZE` at build time.
+pub const GUARD_SIZE: usize = include!(concat!(env!("OUT_DIR"), "/stack_guard_size.rs"));
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+/// Unused stack is filled with this.
+const PAINT: u32 = 0xC0FF_EE55;
+
+/// Painting stops this far below the stack pointer, to keep clear of
+/// `paint()`'s own frame.
+const PAINT_MARGIN: usize = 64;
+
+// MPU RASR
+const RASR_ENABLE: u32 = 1 << 0;
+const RASR_SIZE_SHIFT: u32 = 1;
+const RASR_AP_READ_ONLY: u32 = 0b110 << 24;
+const RASR_XN: u32 = 1 << 28;
+
+// MPU CTRL
+const CTRL_ENABLE: u32 = 1 << 0;
+const CTRL_PRIVDEFENA: u32 = 1 << 2;
+
+/// We only use one region, so take the highest priority one.
+const GUARD_REGION: u32 = 7;
+
+extern "C" {
+    // Defined by cortex-m-rt and memory.x. Only the addresses of these
+    // symbols are meaningful.
+    static _stack_start: u32;
+    static _stack_guard: u32;
+}
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Get the current stack usage.
+pub fn stats() -> StackStats {
+    let (bottom, top) = bounds();
+    let sp = cortex_m::register::msp::read() as usize;
+    let mut addr = bottom + GUARD_SIZE;
+    while addr < top && unsafe { ptr::read_volatile(addr as *const u32) } == PAINT {
+        addr += 4;
+    }
+    StackStats {
+        size: top - bottom,
+        used: top.saturating_sub(sp),
+        high_water: top - addr,
+    }
+}
+
+/// Is `address` in the guard region?
+pub fn is_guard_address(address: u32) -> bool {
+    let (bottom, _) = bounds();
+    let address = address as usize;
+    address >= bottom && address < bottom + GUARD_SIZE
+}
+
+/// Fill the stack between the guard region and the current stack pointer
+/// with the paint pattern. Called first thing at start-up.
+#[inline(never)]
+pub(crate) unsafe fn paint() {
+    let (bottom, _) = bounds();
+    let end = cortex_m::register::msp::read() as usize - PAINT_MARGIN;
+    let mut addr = bottom + GUARD_SIZE;
+    while addr < end {
+        ptr::write_volatile(addr as *mut u32, PAINT);
+        addr += 4;
+    }
+}
+
+/// Make the bottom of the stack read-only, and turn on the MPU. Everything
+/// else keeps the default memory map.
+pub(crate) fn enable_guard(mpu: &MPU) {
+    let (bottom, _) = bounds();
+    // SIZE encodes the region as 2^(SIZE + 1) bytes.
+    let size = GUARD_SIZE.trailing_zeros() - 1;
+    unsafe {
+        mpu.rnr.write(GUARD_REGION);
+        mpu.rbar.write(bottom as u32);
+        mpu.rasr
+            .write(RASR_XN | RASR_AP_READ_ONLY | (size << RASR_SIZE_SHIFT) | RASR_ENABLE);
+        mpu.ctrl.write(CTRL_PRIVDEFENA | CTRL_ENABLE);
+    }
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// The bottom (including the guard) and top of the stack.
+fn bounds() -> (usize, usize) {
+    unsafe {
+        (
+            &_stack_guard as *const u32 as usize,
+            &_stack_start as *const u32 as usize,
+        )
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
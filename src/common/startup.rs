@@ -1,27 +1,68 @@
 //! Handles CPU startup (segment init, etc) that's common to all CPUs.
 //! Chip specific init (like the interrupt table) should be in the relevant <chip> module.
+//!
+//! With the `rtic` feature, the entry point and the handlers an application
+//! framework might want to bind (SVCall, DebugMonitor, PendSV, SysTick and
+//! the peripheral interrupts) are left out. The application calls
+//! `Board::from_peripherals` itself, and forwards the interrupts used by the
+//! crate's services to their `on_interrupt` functions. The fault handlers
+//! stay.
+//!
+//! Without `rtic`, a service's interrupt handler is only here if its
+//! feature is on (`effects` for TIMER2A, `timers` for TIMER3A, `edges` for
+//! GPIOF, `uart0` to `uart7` for the UARTs, `dma` for UART0 to UART2 and
+//! SSI0 to SSI3), so an application which doesn't
+//! use the service keeps the vector for itself.
 
 // ****************************************************************************
 //
 // Imports
 //
 // ****************************************************************************
-extern crate alloc;
-extern crate alloc_cortex_m;
-
-use self::alloc_cortex_m::CortexMHeap;
-
-#[global_allocator]
-static ALLOCATOR: CortexMHeap = CortexMHeap::empty();
 
 use cortex_m;
-use cortex_m_rt::{entry, exception, ExceptionFrame};
+#[cfg(not(feature = "rtic"))]
+use cortex_m_rt::entry;
+use cortex_m_rt::exception;
+#[cfg(not(feature = "rtic"))]
+use tm4c123x_hal::tm4c123x::interrupt;
 
-use board;
-use cpu::{gpio::GpioExt, serial, sysctl::SysctlExt, time::Bps};
+use core::fmt::{self, Write};
 
+use crate::board;
+use crate::board::crash::{self, CrashKind};
+#[cfg(all(not(feature = "rtic"), feature = "dma"))]
+use crate::board::dma;
+#[cfg(all(not(feature = "rtic"), feature = "edges"))]
+use crate::board::edges;
+#[cfg(all(not(feature = "rtic"), feature = "effects"))]
+use crate::board::effects;
+#[cfg(all(not(feature = "rtic"), feature = "timers"))]
+use crate::board::timers;
+#[cfg(all(
+    not(feature = "rtic"),
+    any(
+        feature = "uart0",
+        feature = "uart1",
+        feature = "uart2",
+        feature = "uart3",
+        feature = "uart4",
+        feature = "uart5",
+        feature = "uart6",
+        feature = "uart7"
+    )
+))]
+use crate::board::uart;
+#[cfg(all(not(feature = "rtic"), feature = "alloc"))]
+use crate::common::heap;
+#[cfg(not(feature = "rtic"))]
+use crate::common::stack;
+
+#[cfg(not(feature = "rtic"))]
 extern "Rust" {
-    fn stellaris_main(board: board::Board);
+    // Generated by `#[stellaris_launchpad::main]`, which builds the `Board`
+    // with `init_board` and the application's `BoardConfig`.
+    fn stellaris_main() -> !;
 }
 
 // ****************************************************************************
@@ -30,7 +71,12 @@ extern "Rust" {
 //
 // ****************************************************************************
 
-// None
+/// The types an entry point marked with `#[stellaris_launchpad::main]` may
+/// return (besides `!`).
+pub trait MainReturn {
+    /// Called when the entry point returns. Never comes back.
+    fn exit(self) -> !;
+}
 
 // ****************************************************************************
 //
@@ -67,15 +113,51 @@ extern "Rust" {
 ///
 /// Copies global .data init from flash to SRAM and then
 /// zeros the bss segment.
+#[cfg(not(feature = "rtic"))]
 #[entry]
 unsafe fn call_main() -> ! {
-    let start = cortex_m_rt::heap_start() as usize;
-    let size = 24576; // in bytes
-    ALLOCATOR.init(start, size);
-    let board = board::Board::new();
-    stellaris_main(board);
-    loop {
-        cortex_m::asm::wfi();
+    stack::paint();
+    #[cfg(feature = "alloc")]
+    heap::init();
+    stellaris_main()
+}
+
+/// Used by `#[stellaris_launchpad::main]` to initialise the board with the
+/// application's configuration.
+#[doc(hidden)]
+#[cfg(not(feature = "rtic"))]
+pub fn init_board(config: board::BoardConfig) -> board::Board {
+    board::Board::new(config)
+}
+
+/// Returning from the entry point parks the CPU.
+impl MainReturn for () {
+    fn exit(self) -> ! {
+        idle()
+    }
+}
+
+/// Returning `Ok` from the entry point parks the CPU. Returning an `Err`
+/// reports it on UART0, then flashes the LED.
+impl<E> MainReturn for Result<(), E>
+where
+    E: fmt::Debug,
+{
+    fn exit(self) -> ! {
+        match self {
+            Ok(()) => idle(),
+            Err(e) => {
+                cortex_m::interrupt::disable();
+                crash::record(
+                    CrashKind::MainReturnedError,
+                    0,
+                    None,
+                    format_args!("{:?}", e),
+                );
+                let _ = writeln!(board::fault_uart(), "main() returned an error: {:?}", e);
+                board::panic();
+            }
+        }
     }
 }
 
@@ -85,35 +167,11 @@ unsafe fn call_main() -> ! {
 //
 // ****************************************************************************
 
-/// A HardFault is an exception that occurs because of an error during
-/// exception processing, or because an exception cannot be managed by any
-/// other exception mechanism. HardFaults have a fixed priority of -1, meaning
-/// they have higher priority than any exception with configurable priority.
-#[exception]
-unsafe fn HardFault(sf: &ExceptionFrame) -> ! {
-    // Need ITM support for this to work
-    // iprintln!("EXCEPTION {:?} @ PC=0x{:08x}", Exception::active(), sf.pc);
-
-    let peripherals = tm4c123x_hal::Peripherals::steal();
-    let sysctl = peripherals.SYSCTL.constrain();
-    let mut pins = peripherals.GPIO_PORTA.split(&sysctl.power_control);
-    use core::fmt::Write;
-    let mut uart = serial::Serial::uart0(
-        peripherals.UART0,
-        pins.pa1.into_af_push_pull(&mut pins.control),
-        pins.pa0.into_af_push_pull(&mut pins.control),
-        (),
-        (),
-        Bps(115200),
-        serial::NewlineMode::SwapLFtoCRLF,
-        board::clocks(),
-        &sysctl.power_control,
-    );
-    writeln!(uart, "SF: {:?}", sf).unwrap();
-
-    cortex_m::asm::bkpt();
-
-    loop {}
+/// Sleep forever.
+fn idle() -> ! {
+    loop {
+        cortex_m::asm::wfi();
+    }
 }
 
 /// A Non Maskable Interrupt (NMI) can be signalled by a peripheral or
@@ -127,46 +185,21 @@ fn NonMaskableInt() {
     // Do nothing
 }
 
-/// A MemManage fault is an exception that occurs because of a memory
-/// protection related fault. The the fixed memory protection constraints
-/// determines this fault, for both instruction and data memory transactions.
-/// This fault is always used to abort instruction accesses to Execute Never
-/// (XN) memory regions.
-#[exception]
-fn MemoryManagement() {
-    board::panic();
-}
-
-/// A BusFault is an exception that occurs because of a memory related fault
-/// for an instruction or data memory transaction. This might be from an error
-/// detected on a bus in the memory system.
-#[exception]
-fn BusFault() {
-    board::panic();
-}
-
-/// A UsageFault is an exception that occurs because of a fault related to instruction execution. This includes:
-/// * an undefined instruction
-/// * an illegal unaligned access
-/// * invalid state on instruction execution
-/// * an error on exception return.
-/// The following can cause a UsageFault when the core is configured to report them:
-/// * an unaligned address on word and halfword memory access
-/// * division by zero.
-#[exception]
-fn UsageFault() {
-    board::panic();
-}
+// HardFault, MemoryManagement, BusFault and UsageFault are in
+// `common::fault`, as they need an assembly shim to find the exception frame
+// and to get off the stack if it has overflowed.
 
 /// A supervisor call (SVC) is an exception that is triggered by the SVC
 /// instruction. In an OS environment, applications can use SVC instructions
 /// to access OS kernel functions and device drivers.
+#[cfg(not(feature = "rtic"))]
 #[exception]
 fn SVCall() {
     // Nothing
 }
 
 /// Debug monitor interrupt handler.
+#[cfg(not(feature = "rtic"))]
 #[exception]
 fn DebugMonitor() {
     // Nothing
@@ -175,6 +208,7 @@ fn DebugMonitor() {
 /// PendSV is an interrupt-driven request for system-level service. In an OS
 /// environment, use PendSV for context switching when no other exception is
 /// active.
+#[cfg(not(feature = "rtic"))]
 #[exception]
 fn PendSV() {
     // Nothing
@@ -183,14 +217,138 @@ fn PendSV() {
 /// A SysTick exception is an exception the system timer generates when it
 /// reaches zero. Software can also generate a SysTick exception. In an OS
 /// environment, the processor can use this exception as system tick.
+#[cfg(not(feature = "rtic"))]
 #[exception]
 fn SysTick() {
     // Nothing
 }
 
+/// Ticks the LED effects engine (see `board::effects`).
+#[cfg(all(not(feature = "rtic"), feature = "effects"))]
+#[interrupt]
+fn TIMER2A() {
+    effects::on_interrupt();
+}
+
+/// Runs the software timers (see `board::timers`).
+#[cfg(all(not(feature = "rtic"), feature = "timers"))]
+#[interrupt]
+fn TIMER3A() {
+    timers::on_interrupt();
+}
+
+/// Edges on the switches (see `board::edges`).
+#[cfg(all(not(feature = "rtic"), feature = "edges"))]
+#[interrupt]
+fn GPIOF() {
+    edges::on_interrupt();
+}
+
+/// Services UART0 (see `board::uart`) and its DMA channels (see
+/// `board::dma`), whichever of the two are in use.
+#[cfg(all(not(feature = "rtic"), any(feature = "uart0", feature = "dma")))]
+#[interrupt]
+fn UART0() {
+    #[cfg(feature = "dma")]
+    dma::on_interrupt();
+    #[cfg(feature = "uart0")]
+    uart::on_interrupt(uart::Port::Uart0);
+}
+
+/// Services UART1 (see `board::uart`) and its DMA channels (see
+/// `board::dma`), whichever of the two are in use.
+#[cfg(all(not(feature = "rtic"), any(feature = "uart1", feature = "dma")))]
+#[interrupt]
+fn UART1() {
+    #[cfg(feature = "dma")]
+    dma::on_interrupt();
+    #[cfg(feature = "uart1")]
+    uart::on_interrupt(uart::Port::Uart1);
+}
+
+/// Services UART2 (see `board::uart`) and its DMA channels (see
+/// `board::dma`), whichever of the two are in use.
+#[cfg(all(not(feature = "rtic"), any(feature = "uart2", feature = "dma")))]
+#[interrupt]
+fn UART2() {
+    #[cfg(feature = "dma")]
+    dma::on_interrupt();
+    #[cfg(feature = "uart2")]
+    uart::on_interrupt(uart::Port::Uart2);
+}
+
+/// Wakes the task reading UART3 (see `board::uart`).
+#[cfg(all(not(feature = "rtic"), feature = "uart3"))]
+#[interrupt]
+fn UART3() {
+    uart::on_interrupt(uart::Port::Uart3);
+}
+
+/// Wakes the task reading UART4 (see `board::uart`).
+#[cfg(all(not(feature = "rtic"), feature = "uart4"))]
+#[interrupt]
+fn UART4() {
+    uart::on_interrupt(uart::Port::Uart4);
+}
+
+/// Wakes the task reading UART5 (see `board::uart`).
+#[cfg(all(not(feature = "rtic"), feature = "uart5"))]
+#[interrupt]
+fn UART5() {
+    uart::on_interrupt(uart::Port::Uart5);
+}
+
+/// Wakes the task reading UART6 (see `board::uart`).
+#[cfg(all(not(feature = "rtic"), feature = "uart6"))]
+#[interrupt]
+fn UART6() {
+    uart::on_interrupt(uart::Port::Uart6);
+}
+
+/// Wakes the task reading UART7 (see `board::uart`).
+#[cfg(all(not(feature = "rtic"), feature = "uart7"))]
+#[interrupt]
+fn UART7() {
+    uart::on_interrupt(uart::Port::Uart7);
+}
+
+/// Finishes DMA transfers on SSI0 (see `board::dma`).
+#[cfg(all(not(feature = "rtic"), feature = "dma"))]
+#[interrupt]
+fn SSI0() {
+    dma::on_interrupt();
+}
+
+/// Finishes DMA transfers on SSI1 (see `board::dma`).
+#[cfg(all(not(feature = "rtic"), feature = "dma"))]
+#[interrupt]
+fn SSI1() {
+    dma::on_interrupt();
+}
+
+/// Finishes DMA transfers on SSI2 (see `board::dma`).
+#[cfg(all(not(feature = "rtic"), feature = "dma"))]
+#[interrupt]
+fn SSI2() {
+    dma::on_interrupt();
+}
+
+/// Finishes DMA transfers on SSI3 (see `board::dma`).
+#[cfg(all(not(feature = "rtic"), feature = "dma"))]
+#[interrupt]
+fn SSI3() {
+    dma::on_interrupt();
+}
+
 /// A place-holder ISR used when we have nothing better to use.
 #[exception]
-unsafe fn DefaultHandler(_irq_number: i16) -> ! {
+unsafe fn DefaultHandler(irq_number: i16) -> ! {
+    crash::record(
+        CrashKind::UnexpectedInterrupt,
+        irq_number as u32,
+        None,
+        format_args!("IRQ {}", irq_number),
+    );
     board::panic();
 }
 
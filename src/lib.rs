@@ -11,8 +11,12 @@
 //! (inc PWM) and GPIO seem to work. I'm gradually trying to follow the
 //! example set by japaric in his [F3 crate](https://github.com/japaric/f3)
 //! for the STM32F3 Discovery Board.
+//!
+//! Applications mark their entry point with `#[stellaris_launchpad::main]`;
+//! it is handed the initialised `board::Board` once start-up is complete.
 
 #![no_std]
+#![cfg_attr(feature = "alloc", feature(alloc_error_handler))]
 #![warn(dead_code)]
 #![deny(missing_docs)]
 
@@ -25,6 +29,12 @@
 extern crate cortex_m;
 extern crate cortex_m_rt;
 extern crate embedded_hal;
+extern crate nb;
+extern crate stellaris_launchpad_colour;
+extern crate stellaris_launchpad_log;
+extern crate stellaris_launchpad_macros;
+extern crate stellaris_launchpad_shell;
+extern crate stellaris_launchpad_telemetry;
 pub extern crate tm4c123x_hal;
 extern crate volatile_register;
 
@@ -41,6 +51,16 @@ pub use tm4c123x_hal as cpu;
 
 pub use tm4c123x_hal::delay;
 
+pub use stellaris_launchpad_colour as colour;
+
+pub use stellaris_launchpad_log as log;
+
+pub use stellaris_launchpad_shell as shell;
+
+pub use stellaris_launchpad_telemetry as telemetry;
+
+pub use stellaris_launchpad_macros::main;
+
 // ****************************************************************************
 //
 // Public Types
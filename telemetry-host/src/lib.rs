@@ -0,0 +1,439 @@
+//! The PC end of the stellaris-launchpad telemetry protocol (see the
+//! `stellaris-launchpad-telemetry` crate): read framed messages from the
+//! Launchpad's serial port, and frame commands to send back.
+//!
+//! ```no_run
+//! use std::io::Write;
+//! use stellaris_launchpad_telemetry_host::{encode_command, Command, Messages};
+//!
+//! let port = std::fs::OpenOptions::new()
+//!     .read(true)
+//!     .write(true)
+//!     .open("/dev/ttyACM0")
+//!     .unwrap();
+//! (&port).write_all(&encode_command(&Command::Ping { sequence: 1 })).unwrap();
+//! for message in Messages::new(&port) {
+//!     match message {
+//!         Ok(message) => println!("{:?}", message),
+//!         Err(e) => eprintln!("{}", e),
+//!     }
+//! }
+//! ```
+//!
+//! It needs `std`, so test it on the host:
+//!
+//! ```text
+//! cargo test -p stellaris-launchpad-telemetry-host --target x86_64-unknown-linux-gnu
+//! ```
+
+#![deny(missing_docs)]
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use std::fmt;
+use std::io::{self, Read};
+
+use stellaris_launchpad_telemetry::{self as protocol, FrameDecoder};
+
+pub use stellaris_launchpad_telemetry::{Command, Error as ProtocolError, Telemetry};
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A `Telemetry` message which owns its strings, so it can outlive the
+/// frame it came in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// See `Telemetry::Hello`.
+    Hello {
+        /// The firmware's name and version
+        firmware: String,
+        /// Why the chip came out of reset
+        reset_cause: String,
+    },
+    /// See `Telemetry::Heartbeat`.
+    Heartbeat {
+        /// Milliseconds since boot
+        uptime_ms: u64,
+        /// Times round the main loop
+        loops: u32,
+    },
+    /// See `Telemetry::Log`.
+    Log {
+        /// 0 (error) to 4 (trace)
+        level: u8,
+        /// The module it came from
+        module: String,
+        /// The message
+        text: String,
+    },
+    /// See `Telemetry::Sample`.
+    Sample {
+        /// What was measured
+        channel: u8,
+        /// The measurement
+        value: f32,
+    },
+    /// See `Telemetry::Pong`.
+    Pong {
+        /// The ping's sequence number
+        sequence: u32,
+    },
+    /// See `Telemetry::Ack`.
+    Ack {
+        /// The command's sequence number
+        sequence: u32,
+        /// Whether the command was carried out
+        ok: bool,
+    },
+}
+
+/// Something which went wrong reading messages.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading from the port failed.
+    Io(io::Error),
+    /// A frame was damaged, or didn't hold a valid message. The next frame
+    /// may well be fine.
+    Protocol(ProtocolError),
+}
+
+/// Turns bytes from the Launchpad, in whatever chunks they arrive, into
+/// messages.
+#[derive(Default)]
+pub struct Decoder {
+    frames: FrameDecoder,
+}
+
+/// An iterator over the messages coming from a reader. It ends when the
+/// reader does.
+pub struct Messages<R> {
+    reader: R,
+    decoder: Decoder,
+    pending: std::vec::IntoIter<Result<Message, ProtocolError>>,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const READ_SIZE: usize = 256;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Frame a command, ready to write to the Launchpad.
+pub fn encode_command(command: &Command) -> Vec<u8> {
+    encode(command)
+}
+
+/// Frame a message as the Launchpad would - handy for simulators and
+/// tests.
+pub fn encode_telemetry(message: &Telemetry) -> Vec<u8> {
+    encode(message)
+}
+
+impl Decoder {
+    /// A decoder waiting for the start of a frame.
+    pub fn new() -> Decoder {
+        Decoder::default()
+    }
+
+    /// Add some bytes, returning the messages (or errors) in any frames
+    /// they finish.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Result<Message, ProtocolError>> {
+        bytes
+            .iter()
+            .filter_map(|&byte| {
+                self.frames.push(byte).map(|frame| {
+                    frame
+                        .and_then(protocol::from_bytes::<Telemetry>)
+                        .map(Message::from)
+                })
+            })
+            .collect()
+    }
+}
+
+impl<R: Read> Messages<R> {
+    /// Read messages from `reader`.
+    pub fn new(reader: R) -> Messages<R> {
+        Messages {
+            reader,
+            decoder: Decoder::new(),
+            pending: Vec::new().into_iter(),
+        }
+    }
+
+    /// Stop, returning the reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for Messages<R> {
+    type Item = Result<Message, Error>;
+
+    fn next(&mut self) -> Option<Result<Message, Error>> {
+        let mut buffer = [0u8; READ_SIZE];
+        loop {
+            if let Some(result) = self.pending.next() {
+                return Some(result.map_err(Error::Protocol));
+            }
+            match self.reader.read(&mut buffer) {
+                Ok(0) => return None,
+                Ok(n) => self.pending = self.decoder.feed(&buffer[..n]).into_iter(),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Some(Err(Error::Io(e))),
+            }
+        }
+    }
+}
+
+impl<'a> From<Telemetry<'a>> for Message {
+    fn from(message: Telemetry<'a>) -> Message {
+        match message {
+            Telemetry::Hello {
+                firmware,
+                reset_cause,
+            } => Message::Hello {
+                firmware: firmware.to_owned(),
+                reset_cause: reset_cause.to_owned(),
+            },
+            Telemetry::Heartbeat { uptime_ms, loops } => Message::Heartbeat { uptime_ms, loops },
+            Telemetry::Log {
+                level,
+                module,
+                text,
+            } => Message::Log {
+                level,
+                module: module.to_owned(),
+                text: text.to_owned(),
+            },
+            Telemetry::Sample { channel, value } => Message::Sample { channel, value },
+            Telemetry::Pong { sequence } => Message::Pong { sequence },
+            Telemetry::Ack { sequence, ok } => Message::Ack { sequence, ok },
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "reading from the Launchpad: {}", e),
+            Error::Protocol(e) => write!(f, "bad frame from the Launchpad: {:?}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Protocol(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<ProtocolError> for Error {
+    fn from(e: ProtocolError) -> Error {
+        Error::Protocol(e)
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+fn encode<M: protocol::Encode>(message: &M) -> Vec<u8> {
+    let mut buffer = [0u8; protocol::MAX_FRAME];
+    let len = protocol::encode_frame(message, &mut buffer).expect("message too long for a frame");
+    buffer[..len].to_vec()
+}
+
+// ****************************************************************************
+//
+// Tests
+//
+// ****************************************************************************
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn telemetry() -> Vec<Telemetry<'static>> {
+        vec![
+            Telemetry::Hello {
+                firmware: "stellaris-launchpad 0.11.2",
+                reset_cause: "power-on",
+            },
+            Telemetry::Heartbeat {
+                uptime_ms: 123_456_789_012,
+                loops: 42,
+            },
+            Telemetry::Log {
+                level: 1,
+                module: "app::motor",
+                text: "stalled \u{2013} backing off",
+            },
+            Telemetry::Sample {
+                channel: 2,
+                value: -0.5,
+            },
+            Telemetry::Pong { sequence: 0 },
+            Telemetry::Ack {
+                sequence: u32::MAX,
+                ok: true,
+            },
+        ]
+    }
+
+    fn stream() -> Vec<u8> {
+        telemetry().iter().flat_map(encode_telemetry).collect()
+    }
+
+    #[test]
+    fn every_message_round_trips() {
+        let wire = stream();
+        let got: Vec<_> = Decoder::new().feed(&wire);
+        let want: Vec<_> = telemetry()
+            .into_iter()
+            .map(|m| Ok(Message::from(m)))
+            .collect();
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn chunking_does_not_matter() {
+        let wire = stream();
+        let whole = Decoder::new().feed(&wire);
+        for chunk in 1..8 {
+            let mut decoder = Decoder::new();
+            let got: Vec<_> = wire.chunks(chunk).flat_map(|c| decoder.feed(c)).collect();
+            assert_eq!(got, whole, "chunks of {}", chunk);
+        }
+    }
+
+    #[test]
+    fn commands_round_trip() {
+        let commands = [
+            Command::Ping { sequence: 1 },
+            Command::SetLed {
+                sequence: 2,
+                red: true,
+                green: true,
+                blue: false,
+            },
+            Command::SetHeartbeat {
+                sequence: 3,
+                period_ms: 250,
+            },
+            Command::Reset { sequence: 4 },
+        ];
+        let mut frames = FrameDecoder::new();
+        for command in commands.iter() {
+            let wire = encode_command(command);
+            let mut got = None;
+            for &byte in &wire {
+                if let Some(frame) = frames.push(byte) {
+                    got = Some(protocol::from_bytes::<Command>(frame.unwrap()).unwrap());
+                }
+            }
+            assert_eq!(got.as_ref(), Some(command));
+        }
+    }
+
+    #[test]
+    fn recovers_after_junk_and_damage() {
+        let mut wire = b"Hello, world! Loops = 3\r\n".to_vec();
+        wire.push(0);
+        let mut damaged = encode_telemetry(&Telemetry::Pong { sequence: 300 });
+        damaged[1] ^= 0x10;
+        wire.extend(damaged);
+        wire.extend(encode_telemetry(&Telemetry::Pong { sequence: 301 }));
+
+        let got = Decoder::new().feed(&wire);
+        assert_eq!(
+            got,
+            vec![
+                Err(ProtocolError::Cobs),
+                Err(ProtocolError::Crc),
+                Ok(Message::Pong { sequence: 301 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn starting_mid_frame_loses_one_frame() {
+        let wire = stream();
+        let got = Decoder::new().feed(&wire[3..]);
+        assert_eq!(got.len(), telemetry().len());
+        assert!(got[0].is_err());
+        assert!(got[1..].iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn messages_reads_until_the_end() {
+        let wire = stream();
+        let got: Vec<_> = Messages::new(&wire[..]).map(|m| m.unwrap()).collect();
+        assert_eq!(got.len(), telemetry().len());
+        assert_eq!(got[4], Message::Pong { sequence: 0 });
+    }
+
+    #[test]
+    fn io_errors_are_passed_on() {
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::TimedOut, "unplugged"))
+            }
+        }
+        let mut messages = Messages::new(Broken);
+        match messages.next() {
+            Some(Err(Error::Io(e))) => assert_eq!(e.kind(), io::ErrorKind::TimedOut),
+            other => panic!("got {:?}", other),
+        }
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************
@@ -0,0 +1,989 @@
+//! The binary protocol between a stellaris-launchpad application and a PC:
+//! the messages, how they're encoded, and how they're framed on the wire.
+//! It's `no_std`, so the firmware and the host tools (see the
+//! `stellaris-launchpad-telemetry-host` crate) share it, and it can be unit
+//! tested on the host:
+//!
+//! ```text
+//! cargo test -p stellaris-launchpad-telemetry --target x86_64-unknown-linux-gnu
+//! ```
+//!
+//! A message is encoded the way `postcard` encodes a serde type: integers
+//! wider than a byte as LEB128 varints (zig-zagged if signed), `bool` and
+//! `u8` as a byte, `f32` as four little-endian bytes, strings and byte
+//! slices as a varint length and then the bytes, and an enum as a varint
+//! variant index and then its fields in order. The frame is that, then a
+//! CRC-16/CCITT-FALSE of it (little-endian), COBS-encoded so there are no
+//! zero bytes, then a zero byte to end the frame.
+//!
+//! ```
+//! # use stellaris_launchpad_telemetry::*;
+//! let mut wire = [0u8; MAX_FRAME];
+//! let len = encode_frame(&Command::Ping { sequence: 7 }, &mut wire).unwrap();
+//!
+//! let mut frames = FrameDecoder::new();
+//! let mut got = None;
+//! for &byte in &wire[..len] {
+//!     if let Some(payload) = frames.push(byte) {
+//!         got = Some(from_bytes::<Command>(payload.unwrap()).unwrap());
+//!     }
+//! }
+//! assert_eq!(got, Some(Command::Ping { sequence: 7 }));
+//! ```
+
+#![no_std]
+#![deny(missing_docs)]
+
+// ****************************************************************************
+//
+// Imports
+//
+// ****************************************************************************
+
+use core::convert::TryFrom;
+use core::str;
+
+// ****************************************************************************
+//
+// Public Types
+//
+// ****************************************************************************
+
+/// A message from the Launchpad to the PC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Telemetry<'a> {
+    /// Sent at start-up.
+    Hello {
+        /// The firmware's name and version
+        firmware: &'a str,
+        /// Why the chip came out of reset, as the firmware sees fit to say
+        reset_cause: &'a str,
+    },
+    /// Sent regularly, to show the firmware is alive.
+    Heartbeat {
+        /// Milliseconds since boot
+        uptime_ms: u64,
+        /// Times round the main loop
+        loops: u32,
+    },
+    /// A log message.
+    Log {
+        /// 0 (error) to 4 (trace), as `log::Level`
+        level: u8,
+        /// The module it came from
+        module: &'a str,
+        /// The message
+        text: &'a str,
+    },
+    /// A measurement.
+    Sample {
+        /// What was measured (an ADC channel, say)
+        channel: u8,
+        /// The measurement
+        value: f32,
+    },
+    /// The answer to `Command::Ping`.
+    Pong {
+        /// The ping's sequence number
+        sequence: u32,
+    },
+    /// The answer to any other command.
+    Ack {
+        /// The command's sequence number
+        sequence: u32,
+        /// Whether the command was carried out
+        ok: bool,
+    },
+}
+
+/// A message from the PC to the Launchpad. Each carries a sequence number,
+/// which comes back in the answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Answer with `Telemetry::Pong`.
+    Ping {
+        /// Sequence number
+        sequence: u32,
+    },
+    /// Set the tri-colour LED.
+    SetLed {
+        /// Sequence number
+        sequence: u32,
+        /// Red on?
+        red: bool,
+        /// Green on?
+        green: bool,
+        /// Blue on?
+        blue: bool,
+    },
+    /// Send `Telemetry::Heartbeat` every so often (0 to stop).
+    SetHeartbeat {
+        /// Sequence number
+        sequence: u32,
+        /// Milliseconds between heartbeats
+        period_ms: u32,
+    },
+    /// Reset the chip.
+    Reset {
+        /// Sequence number
+        sequence: u32,
+    },
+}
+
+/// Something wrong with a message or a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// There wasn't room for the encoded message or frame.
+    BufferFull,
+    /// The message ended early.
+    Truncated,
+    /// The message carried on after it should have ended.
+    TrailingBytes,
+    /// A varint, enum variant, `bool` or string wasn't valid.
+    Invalid,
+    /// The COBS encoding was broken.
+    Cobs,
+    /// The CRC didn't match, so the frame was damaged.
+    Crc,
+    /// The frame was longer than `MAX_FRAME`.
+    TooLong,
+}
+
+/// Somewhere to encode a message. See `Encode`.
+#[derive(Debug)]
+pub struct Writer<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+/// An encoded message being decoded. See `Decode`.
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+/// Something which can be encoded into a message.
+pub trait Encode {
+    /// Encode it onto the end of `w`.
+    fn encode(&self, w: &mut Writer) -> Result<(), Error>;
+}
+
+/// Something which can be decoded from a message. It can borrow strings
+/// from the message.
+pub trait Decode<'a>: Sized {
+    /// Decode it from the front of `r`.
+    fn decode(r: &mut Reader<'a>) -> Result<Self, Error>;
+}
+
+/// Collects frames from a stream of bytes, one byte at a time.
+pub struct FrameDecoder {
+    buffer: [u8; MAX_FRAME],
+    len: usize,
+    overflowed: bool,
+}
+
+// ****************************************************************************
+//
+// Public Data
+//
+// ****************************************************************************
+
+/// The longest encoded message.
+pub const MAX_PAYLOAD: usize = 250;
+
+/// The longest frame on the wire, including the zero byte at the end.
+pub const MAX_FRAME: usize = MAX_PAYLOAD + CRC_LEN + (MAX_PAYLOAD + CRC_LEN) / 254 + 1 + 1;
+
+// ****************************************************************************
+//
+// Private Types
+//
+// ****************************************************************************
+
+// None
+
+// ****************************************************************************
+//
+// Private Data
+//
+// ****************************************************************************
+
+const CRC_LEN: usize = 2;
+
+// ****************************************************************************
+//
+// Public Functions
+//
+// ****************************************************************************
+
+/// Encode `message` into `buffer`, returning the part of it used.
+pub fn to_slice<'b, M: Encode>(message: &M, buffer: &'b mut [u8]) -> Result<&'b mut [u8], Error> {
+    let mut w = Writer::new(buffer);
+    message.encode(&mut w)?;
+    let len = w.len;
+    Ok(&mut buffer[..len])
+}
+
+/// Decode a whole message from `bytes`.
+pub fn from_bytes<'a, M: Decode<'a>>(bytes: &'a [u8]) -> Result<M, Error> {
+    let mut r = Reader::new(bytes);
+    let message = M::decode(&mut r)?;
+    if r.remaining() != 0 {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(message)
+}
+
+/// Encode `message` as a complete frame in `buffer` (which needs no more
+/// than `MAX_FRAME` bytes), returning the frame's length.
+pub fn encode_frame<M: Encode>(message: &M, buffer: &mut [u8]) -> Result<usize, Error> {
+    let mut payload = [0u8; MAX_PAYLOAD + CRC_LEN];
+    let len = to_slice(message, &mut payload[..MAX_PAYLOAD])?.len();
+    let crc = crc16(&payload[..len]);
+    payload[len..len + CRC_LEN].copy_from_slice(&crc.to_le_bytes());
+    let encoded = cobs_encode(&payload[..len + CRC_LEN], buffer)?;
+    *buffer.get_mut(encoded).ok_or(Error::BufferFull)? = 0;
+    Ok(encoded + 1)
+}
+
+/// The CRC-16/CCITT-FALSE of `data` (polynomial 0x1021, starting from
+/// 0xFFFF).
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &byte in data {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// COBS-encode `input` into `output`, returning how many bytes that took
+/// (at most one more than every 254 bytes of input, plus one). Doesn't add
+/// the zero byte which ends a frame.
+pub fn cobs_encode(input: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+    let mut code_at = 0;
+    let mut out = 1;
+    let mut code = 1u8;
+    for &byte in input {
+        if byte == 0 {
+            *output.get_mut(code_at).ok_or(Error::BufferFull)? = code;
+            code_at = out;
+            out += 1;
+            code = 1;
+        } else {
+            *output.get_mut(out).ok_or(Error::BufferFull)? = byte;
+            out += 1;
+            code += 1;
+            if code == 0xFF {
+                *output.get_mut(code_at).ok_or(Error::BufferFull)? = code;
+                code_at = out;
+                out += 1;
+                code = 1;
+            }
+        }
+    }
+    *output.get_mut(code_at).ok_or(Error::BufferFull)? = code;
+    Ok(out)
+}
+
+/// Undo `cobs_encode`, in place (the frame's zero byte should already be
+/// gone). Returns the length of the decoded data.
+pub fn cobs_decode_in_place(buffer: &mut [u8]) -> Result<usize, Error> {
+    let mut read = 0;
+    let mut write = 0;
+    while read < buffer.len() {
+        let code = buffer[read] as usize;
+        if code == 0 || read + code > buffer.len() {
+            return Err(Error::Cobs);
+        }
+        read += 1;
+        for _ in 1..code {
+            if buffer[read] == 0 {
+                return Err(Error::Cobs);
+            }
+            buffer[write] = buffer[read];
+            write += 1;
+            read += 1;
+        }
+        // A block shorter than the most a block can hold ends with a zero,
+        // unless it's the last.
+        if code != 0xFF && read != buffer.len() {
+            buffer[write] = 0;
+            write += 1;
+        }
+    }
+    Ok(write)
+}
+
+impl<'a> Writer<'a> {
+    /// Write into `buffer`, from the start.
+    pub fn new(buffer: &'a mut [u8]) -> Writer<'a> {
+        Writer { buffer, len: 0 }
+    }
+
+    /// How much has been written.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Has nothing been written yet?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Append some bytes as they are.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let end = self.len + bytes.len();
+        self.buffer
+            .get_mut(self.len..end)
+            .ok_or(Error::BufferFull)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Append an unsigned LEB128 varint.
+    pub fn write_varint(&mut self, mut value: u64) -> Result<(), Error> {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                return self.write_bytes(&[byte]);
+            }
+            self.write_bytes(&[byte | 0x80])?;
+        }
+    }
+}
+
+impl<'a> Reader<'a> {
+    /// Read `bytes` from the start.
+    pub fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, position: 0 }
+    }
+
+    /// How many bytes are left.
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    /// Take the next `len` bytes as they are.
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if len > self.remaining() {
+            return Err(Error::Truncated);
+        }
+        let bytes = &self.bytes[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    /// Take an unsigned LEB128 varint.
+    pub fn read_varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_bytes(1)?[0];
+            let bits = u64::from(byte & 0x7F);
+            if shift == 63 && bits > 1 {
+                return Err(Error::Invalid);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::Invalid)
+    }
+}
+
+impl FrameDecoder {
+    /// An empty decoder.
+    pub const fn new() -> FrameDecoder {
+        FrameDecoder {
+            buffer: [0; MAX_FRAME],
+            len: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Add a byte from the wire. At the end of a frame, this returns the
+    /// message in it (checked, but not decoded - see `from_bytes`) or what
+    /// was wrong with it. Empty frames are skipped; anything else before
+    /// the first zero (from connecting mid-frame, say) comes back as one
+    /// bad frame.
+    pub fn push(&mut self, byte: u8) -> Option<Result<&[u8], Error>> {
+        if byte != 0 {
+            if self.len < self.buffer.len() {
+                self.buffer[self.len] = byte;
+                self.len += 1;
+            } else {
+                self.overflowed = true;
+            }
+            return None;
+        }
+        let len = self.len;
+        self.len = 0;
+        if self.overflowed {
+            self.overflowed = false;
+            return Some(Err(Error::TooLong));
+        }
+        if len == 0 {
+            return None;
+        }
+        Some(Self::check(&mut self.buffer[..len]))
+    }
+
+    /// Throw away any partial frame.
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.overflowed = false;
+    }
+
+    fn check(frame: &mut [u8]) -> Result<&[u8], Error> {
+        let len = cobs_decode_in_place(frame)?;
+        if len < CRC_LEN {
+            return Err(Error::Truncated);
+        }
+        let (payload, crc) = frame[..len].split_at(len - CRC_LEN);
+        if crc16(payload).to_le_bytes() != crc {
+            return Err(Error::Crc);
+        }
+        Ok(payload)
+    }
+}
+
+impl Default for FrameDecoder {
+    fn default() -> FrameDecoder {
+        FrameDecoder::new()
+    }
+}
+
+impl Encode for u8 {
+    fn encode(&self, w: &mut Writer) -> Result<(), Error> {
+        w.write_bytes(&[*self])
+    }
+}
+
+impl<'a> Decode<'a> for u8 {
+    fn decode(r: &mut Reader<'a>) -> Result<u8, Error> {
+        Ok(r.read_bytes(1)?[0])
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, w: &mut Writer) -> Result<(), Error> {
+        w.write_bytes(&[*self as u8])
+    }
+}
+
+impl<'a> Decode<'a> for bool {
+    fn decode(r: &mut Reader<'a>) -> Result<bool, Error> {
+        match u8::decode(r)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::Invalid),
+        }
+    }
+}
+
+impl Encode for u16 {
+    fn encode(&self, w: &mut Writer) -> Result<(), Error> {
+        w.write_varint(u64::from(*self))
+    }
+}
+
+impl<'a> Decode<'a> for u16 {
+    fn decode(r: &mut Reader<'a>) -> Result<u16, Error> {
+        narrow(r.read_varint()?)
+    }
+}
+
+impl Encode for u32 {
+    fn encode(&self, w: &mut Writer) -> Result<(), Error> {
+        w.write_varint(u64::from(*self))
+    }
+}
+
+impl<'a> Decode<'a> for u32 {
+    fn decode(r: &mut Reader<'a>) -> Result<u32, Error> {
+        narrow(r.read_varint()?)
+    }
+}
+
+impl Encode for u64 {
+    fn encode(&self, w: &mut Writer) -> Result<(), Error> {
+        w.write_varint(*self)
+    }
+}
+
+impl<'a> Decode<'a> for u64 {
+    fn decode(r: &mut Reader<'a>) -> Result<u64, Error> {
+        r.read_varint()
+    }
+}
+
+impl Encode for i32 {
+    fn encode(&self, w: &mut Writer) -> Result<(), Error> {
+        i64::from(*self).encode(w)
+    }
+}
+
+impl<'a> Decode<'a> for i32 {
+    fn decode(r: &mut Reader<'a>) -> Result<i32, Error> {
+        let value = i64::decode(r)?;
+        i32::try_from(value).map_err(|_| Error::Invalid)
+    }
+}
+
+impl Encode for i64 {
+    fn encode(&self, w: &mut Writer) -> Result<(), Error> {
+        w.write_varint(((*self << 1) ^ (*self >> 63)) as u64)
+    }
+}
+
+impl<'a> Decode<'a> for i64 {
+    fn decode(r: &mut Reader<'a>) -> Result<i64, Error> {
+        let value = r.read_varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+}
+
+impl Encode for f32 {
+    fn encode(&self, w: &mut Writer) -> Result<(), Error> {
+        w.write_bytes(&self.to_le_bytes())
+    }
+}
+
+impl<'a> Decode<'a> for f32 {
+    fn decode(r: &mut Reader<'a>) -> Result<f32, Error> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(r.read_bytes(4)?);
+        Ok(f32::from_le_bytes(bytes))
+    }
+}
+
+impl Encode for &[u8] {
+    fn encode(&self, w: &mut Writer) -> Result<(), Error> {
+        w.write_varint(self.len() as u64)?;
+        w.write_bytes(self)
+    }
+}
+
+impl<'a> Decode<'a> for &'a [u8] {
+    fn decode(r: &mut Reader<'a>) -> Result<&'a [u8], Error> {
+        let len = r.read_varint()?;
+        if len > r.remaining() as u64 {
+            return Err(Error::Truncated);
+        }
+        r.read_bytes(len as usize)
+    }
+}
+
+impl Encode for &str {
+    fn encode(&self, w: &mut Writer) -> Result<(), Error> {
+        self.as_bytes().encode(w)
+    }
+}
+
+impl<'a> Decode<'a> for &'a str {
+    fn decode(r: &mut Reader<'a>) -> Result<&'a str, Error> {
+        str::from_utf8(<&[u8]>::decode(r)?).map_err(|_| Error::Invalid)
+    }
+}
+
+impl<'t> Encode for Telemetry<'t> {
+    fn encode(&self, w: &mut Writer) -> Result<(), Error> {
+        match *self {
+            Telemetry::Hello {
+                firmware,
+                reset_cause,
+            } => {
+                w.write_varint(0)?;
+                firmware.encode(w)?;
+                reset_cause.encode(w)
+            }
+            Telemetry::Heartbeat { uptime_ms, loops } => {
+                w.write_varint(1)?;
+                uptime_ms.encode(w)?;
+                loops.encode(w)
+            }
+            Telemetry::Log {
+                level,
+                module,
+                text,
+            } => {
+                w.write_varint(2)?;
+                level.encode(w)?;
+                module.encode(w)?;
+                text.encode(w)
+            }
+            Telemetry::Sample { channel, value } => {
+                w.write_varint(3)?;
+                channel.encode(w)?;
+                value.encode(w)
+            }
+            Telemetry::Pong { sequence } => {
+                w.write_varint(4)?;
+                sequence.encode(w)
+            }
+            Telemetry::Ack { sequence, ok } => {
+                w.write_varint(5)?;
+                sequence.encode(w)?;
+                ok.encode(w)
+            }
+        }
+    }
+}
+
+impl<'a> Decode<'a> for Telemetry<'a> {
+    fn decode(r: &mut Reader<'a>) -> Result<Telemetry<'a>, Error> {
+        Ok(match r.read_varint()? {
+            0 => Telemetry::Hello {
+                firmware: Decode::decode(r)?,
+                reset_cause: Decode::decode(r)?,
+            },
+            1 => Telemetry::Heartbeat {
+                uptime_ms: Decode::decode(r)?,
+                loops: Decode::decode(r)?,
+            },
+            2 => Telemetry::Log {
+                level: Decode::decode(r)?,
+                module: Decode::decode(r)?,
+                text: Decode::decode(r)?,
+            },
+            3 => Telemetry::Sample {
+                channel: Decode::decode(r)?,
+                value: Decode::decode(r)?,
+            },
+            4 => Telemetry::Pong {
+                sequence: Decode::decode(r)?,
+            },
+            5 => Telemetry::Ack {
+                sequence: Decode::decode(r)?,
+                ok: Decode::decode(r)?,
+            },
+            _ => return Err(Error::Invalid),
+        })
+    }
+}
+
+impl Command {
+    /// The command's sequence number.
+    pub fn sequence(&self) -> u32 {
+        match *self {
+            Command::Ping { sequence }
+            | Command::SetLed { sequence, .. }
+            | Command::SetHeartbeat { sequence, .. }
+            | Command::Reset { sequence } => sequence,
+        }
+    }
+}
+
+impl Encode for Command {
+    fn encode(&self, w: &mut Writer) -> Result<(), Error> {
+        match *self {
+            Command::Ping { sequence } => {
+                w.write_varint(0)?;
+                sequence.encode(w)
+            }
+            Command::SetLed {
+                sequence,
+                red,
+                green,
+                blue,
+            } => {
+                w.write_varint(1)?;
+                sequence.encode(w)?;
+                red.encode(w)?;
+                green.encode(w)?;
+                blue.encode(w)
+            }
+            Command::SetHeartbeat {
+                sequence,
+                period_ms,
+            } => {
+                w.write_varint(2)?;
+                sequence.encode(w)?;
+                period_ms.encode(w)
+            }
+            Command::Reset { sequence } => {
+                w.write_varint(3)?;
+                sequence.encode(w)
+            }
+        }
+    }
+}
+
+impl<'a> Decode<'a> for Command {
+    fn decode(r: &mut Reader<'a>) -> Result<Command, Error> {
+        Ok(match r.read_varint()? {
+            0 => Command::Ping {
+                sequence: Decode::decode(r)?,
+            },
+            1 => Command::SetLed {
+                sequence: Decode::decode(r)?,
+                red: Decode::decode(r)?,
+                green: Decode::decode(r)?,
+                blue: Decode::decode(r)?,
+            },
+            2 => Command::SetHeartbeat {
+                sequence: Decode::decode(r)?,
+                period_ms: Decode::decode(r)?,
+            },
+            3 => Command::Reset {
+                sequence: Decode::decode(r)?,
+            },
+            _ => return Err(Error::Invalid),
+        })
+    }
+}
+
+// ****************************************************************************
+//
+// Private Functions
+//
+// ****************************************************************************
+
+/// A varint which must fit a narrower type.
+fn narrow<T: TryFrom<u64>>(value: u64) -> Result<T, Error> {
+    T::try_from(value).map_err(|_| Error::Invalid)
+}
+
+// ****************************************************************************
+//
+// Tests
+//
+// ****************************************************************************
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded<M: Encode>(message: &M) -> ([u8; MAX_PAYLOAD], usize) {
+        let mut buffer = [0u8; MAX_PAYLOAD];
+        let len = to_slice(message, &mut buffer).unwrap().len();
+        (buffer, len)
+    }
+
+    fn cobs_round_trip(data: &[u8]) -> usize {
+        let mut wire = [0u8; 600];
+        let len = cobs_encode(data, &mut wire).unwrap();
+        assert!(!wire[..len].contains(&0));
+        assert!(len <= data.len() + data.len() / 254 + 1);
+        let decoded = cobs_decode_in_place(&mut wire[..len]).unwrap();
+        assert_eq!(&wire[..decoded], data);
+        len
+    }
+
+    #[test]
+    fn crc_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(crc16(b""), 0xFFFF);
+    }
+
+    #[test]
+    fn cobs_known_encodings() {
+        let mut wire = [0u8; 16];
+        let len = cobs_encode(&[0x11, 0x22, 0x00, 0x33], &mut wire).unwrap();
+        assert_eq!(&wire[..len], &[0x03, 0x11, 0x22, 0x02, 0x33]);
+        let len = cobs_encode(&[0x00], &mut wire).unwrap();
+        assert_eq!(&wire[..len], &[0x01, 0x01]);
+        let len = cobs_encode(&[], &mut wire).unwrap();
+        assert_eq!(&wire[..len], &[0x01]);
+    }
+
+    #[test]
+    fn cobs_round_trips() {
+        cobs_round_trip(&[]);
+        cobs_round_trip(&[0, 0, 0]);
+        cobs_round_trip(&[1, 2, 0, 3, 0]);
+        let mut long = [0u8; 520];
+        for (i, byte) in long.iter_mut().enumerate() {
+            *byte = (i % 255) as u8 + 1;
+        }
+        assert_eq!(cobs_round_trip(&long[..254]), 256);
+        assert_eq!(cobs_round_trip(&long[..253]), 254);
+        cobs_round_trip(&long);
+        long[300] = 0;
+        cobs_round_trip(&long);
+    }
+
+    #[test]
+    fn cobs_rejects_damage() {
+        assert_eq!(cobs_decode_in_place(&mut [0x05, 0x11]), Err(Error::Cobs));
+        assert_eq!(cobs_decode_in_place(&mut [0x00]), Err(Error::Cobs));
+        assert_eq!(
+            cobs_decode_in_place(&mut [0x03, 0x11, 0x00]),
+            Err(Error::Cobs)
+        );
+    }
+
+    #[test]
+    fn cobs_reports_full_buffers() {
+        let mut wire = [0u8; 3];
+        assert_eq!(cobs_encode(&[1, 2, 3], &mut wire), Err(Error::BufferFull));
+    }
+
+    #[test]
+    fn varints_match_postcard() {
+        let (buffer, len) = encoded(&300u32);
+        assert_eq!(&buffer[..len], &[0xAC, 0x02]);
+        let (buffer, len) = encoded(&u64::MAX);
+        assert_eq!(len, 10);
+        assert_eq!(from_bytes::<u64>(&buffer[..len]), Ok(u64::MAX));
+        let (buffer, len) = encoded(&-1i32);
+        assert_eq!(&buffer[..len], &[0x01]);
+        let (buffer, len) = encoded(&i64::MIN);
+        assert_eq!(from_bytes::<i64>(&buffer[..len]), Ok(i64::MIN));
+    }
+
+    #[test]
+    fn narrow_types_are_checked() {
+        let (buffer, len) = encoded(&70_000u32);
+        assert_eq!(from_bytes::<u16>(&buffer[..len]), Err(Error::Invalid));
+        assert_eq!(from_bytes::<bool>(&[2]), Err(Error::Invalid));
+        assert_eq!(from_bytes::<u64>(&[0x80; 11]), Err(Error::Invalid));
+        assert_eq!(from_bytes::<u32>(&[0x80]), Err(Error::Truncated));
+        assert_eq!(from_bytes::<u8>(&[1, 2]), Err(Error::TrailingBytes));
+        assert_eq!(from_bytes::<&str>(&[2, 0xFF, 0xFE]), Err(Error::Invalid));
+        assert_eq!(from_bytes::<&[u8]>(&[5, 1]), Err(Error::Truncated));
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            Telemetry::Hello {
+                firmware: "blinky 1.0",
+                reset_cause: "power-on",
+            },
+            Telemetry::Heartbeat {
+                uptime_ms: 1 << 40,
+                loops: 3,
+            },
+            Telemetry::Log {
+                level: 2,
+                module: "app",
+                text: "hello",
+            },
+            Telemetry::Sample {
+                channel: 3,
+                value: 1.25,
+            },
+            Telemetry::Pong { sequence: 0 },
+            Telemetry::Ack {
+                sequence: 9,
+                ok: false,
+            },
+        ];
+        for message in messages.iter() {
+            let (buffer, len) = encoded(message);
+            assert_eq!(
+                from_bytes::<Telemetry>(&buffer[..len]).as_ref(),
+                Ok(message)
+            );
+        }
+        let commands = [
+            Command::Ping { sequence: 1 },
+            Command::SetLed {
+                sequence: 2,
+                red: true,
+                green: false,
+                blue: true,
+            },
+            Command::SetHeartbeat {
+                sequence: 3,
+                period_ms: 1000,
+            },
+            Command::Reset { sequence: 4 },
+        ];
+        for command in commands.iter() {
+            let (buffer, len) = encoded(command);
+            assert_eq!(from_bytes::<Command>(&buffer[..len]).as_ref(), Ok(command));
+        }
+        assert_eq!(from_bytes::<Command>(&[9, 0]), Err(Error::Invalid));
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut wire = [0u8; MAX_FRAME];
+        let command = Command::SetLed {
+            sequence: 0,
+            red: false,
+            green: false,
+            blue: false,
+        };
+        let len = encode_frame(&command, &mut wire).unwrap();
+        assert_eq!(wire[len - 1], 0);
+        assert!(!wire[..len - 1].contains(&0));
+
+        let mut frames = FrameDecoder::new();
+        for &byte in &wire[..len - 1] {
+            assert!(frames.push(byte).is_none());
+        }
+        let payload = frames.push(0).unwrap().unwrap();
+        assert_eq!(from_bytes::<Command>(payload), Ok(command));
+    }
+
+    #[test]
+    fn frames_detect_damage() {
+        let mut wire = [0u8; MAX_FRAME];
+        let len = encode_frame(&Command::Ping { sequence: 5 }, &mut wire).unwrap();
+        wire[2] ^= 0x04;
+        let mut frames = FrameDecoder::new();
+        let mut result = None;
+        for &byte in &wire[..len] {
+            if let Some(r) = frames.push(byte) {
+                result = Some(r.map(|_| ()));
+            }
+        }
+        assert_eq!(result, Some(Err(Error::Crc)));
+    }
+
+    #[test]
+    fn frame_decoder_skips_junk_and_overflow() {
+        let mut frames = FrameDecoder::new();
+        assert!(frames.push(0).is_none());
+        for _ in 0..MAX_FRAME + 10 {
+            assert!(frames.push(0x55).is_none());
+        }
+        assert_eq!(
+            frames.push(0).map(|r| r.map(|_| ())),
+            Some(Err(Error::TooLong))
+        );
+        assert!(frames.push(0x01).is_none());
+        assert_eq!(
+            frames.push(0).map(|r| r.map(|_| ())),
+            Some(Err(Error::Truncated))
+        );
+    }
+
+    #[test]
+    fn largest_message_fits() {
+        let text = core::str::from_utf8(&[b'x'; 240]).unwrap();
+        let message = Telemetry::Log {
+            level: 0,
+            module: "",
+            text,
+        };
+        let mut wire = [0u8; MAX_FRAME];
+        let len = encode_frame(&message, &mut wire).unwrap();
+        assert!(len <= MAX_FRAME);
+        let too_big = Telemetry::Log {
+            level: 0,
+            module: text,
+            text,
+        };
+        assert_eq!(encode_frame(&too_big, &mut wire), Err(Error::BufferFull));
+    }
+}
+
+// ****************************************************************************
+//
+// End Of File
+//
+// ****************************************************************************